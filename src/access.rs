@@ -0,0 +1,26 @@
+use soroban_sdk::{Address, Env};
+use crate::storage_types::{DataKey, RenterAccess, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+// Who may rent `token`, `None` when the listing is public
+pub fn read_renter_access(env: &Env, token: &Address) -> Option<RenterAccess> {
+    let key = DataKey::RenterAccess(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key)
+}
+
+pub fn write_renter_access(env: &Env, token: &Address, access: &Option<RenterAccess>) {
+    let key = DataKey::RenterAccess(token.clone());
+    match access {
+        Some(access) if *access != RenterAccess::Public => {
+            env.storage().persistent().set(&key, access);
+            env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        }
+        _ => env.storage().persistent().remove(&key),
+    }
+}
+
+pub fn remove_renter_access(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::RenterAccess(token.clone()));
+}
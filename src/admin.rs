@@ -1,5 +1,5 @@
-use crate::storage_types::{DataKey, INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
-use soroban_sdk::{Address, Env};
+use crate::storage_types::{DataKey, EntryPoint, Role, TimeUnit, INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
+use soroban_sdk::{vec, Address, Env, Vec};
 
 pub fn write_payment_token(e: &Env, id: &Address) {
     let key = DataKey::PaymentToken;
@@ -13,6 +13,113 @@ pub fn read_payment_token(e: &Env) -> Address {
     e.storage().instance().get(&key).unwrap()
 }
 
+pub fn read_payment_tokens(e: &Env) -> Vec<Address> {
+    let key = DataKey::PaymentTokens;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(vec![e])
+}
+
+pub fn is_payment_token_accepted(e: &Env, token: &Address) -> bool {
+    read_payment_tokens(e).contains(token)
+}
+
+pub fn add_payment_token(e: &Env, token: &Address) -> bool {
+    let mut payment_tokens = read_payment_tokens(e);
+    if payment_tokens.contains(token) {
+        return false
+    }
+
+    payment_tokens.push_back(token.clone());
+    e.storage().instance().set(&DataKey::PaymentTokens, &payment_tokens);
+    true
+}
+
+pub fn remove_payment_token(e: &Env, token: &Address) -> bool {
+    let mut payment_tokens = read_payment_tokens(e);
+    if let Some(o) = payment_tokens.first_index_of(token.clone()) {
+        payment_tokens.remove_unchecked(o);
+        e.storage().instance().set(&DataKey::PaymentTokens, &payment_tokens);
+        return true
+    }
+
+    false
+}
+
+pub fn read_fee_bps(e: &Env) -> u32 {
+    let key = DataKey::FeeBps;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(0)
+}
+
+pub fn write_fee_bps(e: &Env, fee_bps: u32) {
+    let key = DataKey::FeeBps;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &fee_bps);
+}
+
+pub fn read_time_unit(e: &Env) -> TimeUnit {
+    let key = DataKey::TimeUnit;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(TimeUnit::Day)
+}
+
+pub fn write_time_unit(e: &Env, unit: TimeUnit) {
+    let key = DataKey::TimeUnit;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &unit);
+}
+
+pub fn read_royalty_cap(e: &Env) -> u32 {
+    let key = DataKey::RoyaltyCap;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(0)
+}
+
+pub fn write_royalty_cap(e: &Env, cap_bps: u32) {
+    let key = DataKey::RoyaltyCap;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &cap_bps);
+}
+
+pub fn read_settle_bounty(e: &Env) -> u32 {
+    let key = DataKey::SettleBounty;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(0)
+}
+
+pub fn write_settle_bounty(e: &Env, bounty_bps: u32) {
+    let key = DataKey::SettleBounty;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &bounty_bps);
+}
+
+pub fn read_treasury(e: &Env) -> Option<Address> {
+    let key = DataKey::Treasury;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key)
+}
+
+pub fn write_treasury(e: &Env, treasury: &Option<Address>) {
+    let key = DataKey::Treasury;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    match treasury {
+        Some(treasury) => e.storage().instance().set(&key, treasury),
+        None => e.storage().instance().remove(&key),
+    }
+}
+
+pub fn read_accrued_fees(e: &Env, token: &Address) -> u128 {
+    let key = DataKey::AccruedFees(token.clone());
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(0)
+}
+
+pub fn write_accrued_fees(e: &Env, token: &Address, amount: u128) {
+    let key = DataKey::AccruedFees(token.clone());
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &amount);
+}
+
 pub fn has_administrator(e: &Env) -> bool {
     let key = DataKey::Admin;
     e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
@@ -31,6 +138,50 @@ pub fn write_administrator(e: &Env, id: &Address) {
     e.storage().instance().set(&key, id);
 }
 
+// Deployments from before storage was versioned are on version 1
+pub fn read_version(e: &Env) -> u32 {
+    let key = DataKey::Version;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(1)
+}
+
+pub fn write_version(e: &Env, version: u32) {
+    let key = DataKey::Version;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &version);
+}
+
+pub fn read_pending_admin(e: &Env) -> Option<Address> {
+    let key = DataKey::PendingAdmin;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key)
+}
+
+pub fn write_pending_admin(e: &Env, id: &Option<Address>) {
+    let key = DataKey::PendingAdmin;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    match id {
+        Some(id) => e.storage().instance().set(&key, id),
+        None => e.storage().instance().remove(&key),
+    }
+}
+
+// A role nobody was granted stays with the admin
+pub fn read_role(e: &Env, role: Role) -> Address {
+    let key = DataKey::Role(role);
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or_else(|| read_administrator(e))
+}
+
+pub fn write_role(e: &Env, role: Role, id: &Option<Address>) {
+    let key = DataKey::Role(role);
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    match id {
+        Some(id) => e.storage().instance().set(&key, id),
+        None => e.storage().instance().remove(&key),
+    }
+}
+
 pub fn pause_rent(e: &Env) {
     let key = DataKey::Paused;
     e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
@@ -41,4 +192,23 @@ pub fn resume_rent(e: &Env) {
     let key = DataKey::Paused;
     e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
     e.storage().instance().set(&key, &false);
-}
\ No newline at end of file
+}
+
+pub fn is_paused(e: &Env) -> bool {
+    let key = DataKey::Paused;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(false)
+}
+
+pub fn read_pausable(e: &Env, entry_point: EntryPoint) -> bool {
+    let key = DataKey::Pausable(entry_point);
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    // New listings and rentals are blocked by default, ways out of a lease are not
+    e.storage().instance().get(&key).unwrap_or(matches!(entry_point, EntryPoint::Lease | EntryPoint::Rent | EntryPoint::Extend | EntryPoint::Offer | EntryPoint::Reserve))
+}
+
+pub fn write_pausable(e: &Env, entry_point: EntryPoint, pausable: bool) {
+    let key = DataKey::Pausable(entry_point);
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &pausable);
+}
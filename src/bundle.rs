@@ -0,0 +1,40 @@
+use soroban_sdk::{Address, Env, Vec};
+use crate::storage_types::{DataKey, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+// A bundle is leased under its first token, every member points back to it
+pub fn write_bundle(env: &Env, bundle: &Address, tokens: &Vec<Address>) {
+    let key = DataKey::Bundle(bundle.clone());
+    env.storage().persistent().set(&key, tokens);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+
+    for token in tokens.iter() {
+        let key = DataKey::BundleOf(token);
+        env.storage().persistent().set(&key, bundle);
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+}
+
+pub fn read_bundle(env: &Env, bundle: &Address) -> Option<Vec<Address>> {
+    let key = DataKey::Bundle(bundle.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key)
+}
+
+pub fn read_bundle_of(env: &Env, token: &Address) -> Option<Address> {
+    let key = DataKey::BundleOf(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key)
+}
+
+pub fn remove_bundle(env: &Env, bundle: &Address) {
+    if let Some(tokens) = read_bundle(env, bundle) {
+        for token in tokens.iter() {
+            env.storage().persistent().remove(&DataKey::BundleOf(token));
+        }
+        env.storage().persistent().remove(&DataKey::Bundle(bundle.clone()));
+    }
+}
@@ -0,0 +1,40 @@
+use soroban_sdk::{Address, Env};
+use crate::storage_types::{DataKey, Royalty, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+pub fn is_collection_approved(env: &Env, token: &Address) -> bool {
+    let key = DataKey::ApprovedCollection(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        true
+    } else {
+        false
+    }
+}
+
+pub fn approve_collection(env: &Env, token: &Address) {
+    let key = DataKey::ApprovedCollection(token.clone());
+    env.storage().persistent().set(&key, &true);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn revoke_collection(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::ApprovedCollection(token.clone()));
+}
+
+pub fn read_royalty(env: &Env, token: &Address) -> Option<Royalty> {
+    let key = DataKey::Royalty(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key)
+}
+
+pub fn write_royalty(env: &Env, token: &Address, royalty: &Royalty) {
+    let key = DataKey::Royalty(token.clone());
+    env.storage().persistent().set(&key, royalty);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn remove_royalty(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::Royalty(token.clone()));
+}
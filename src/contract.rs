@@ -1,469 +1,2452 @@
+use crate::access::{read_renter_access, remove_renter_access, write_renter_access};
 use crate::admin::{
-    has_administrator, pause_rent, read_administrator, read_payment_token, resume_rent,
-    write_administrator, write_payment_token,
+    add_payment_token, has_administrator, is_paused, is_payment_token_accepted, pause_rent,
+    read_accrued_fees, read_administrator, read_fee_bps, read_pausable, read_payment_token,
+    read_payment_tokens, read_pending_admin, read_role, read_royalty_cap, read_settle_bounty, read_time_unit,
+    read_treasury, read_version, remove_payment_token, resume_rent, write_accrued_fees,
+    write_administrator, write_fee_bps, write_pausable, write_payment_token, write_pending_admin,
+    write_role, write_royalty_cap, write_settle_bounty, write_time_unit, write_treasury, write_version,
 };
+use crate::bundle::{read_bundle, read_bundle_of, remove_bundle, write_bundle};
+use crate::collection::{
+    approve_collection, is_collection_approved, read_royalty, remove_royalty, revoke_collection,
+    write_royalty,
+};
+use crate::errors::LienError;
+use crate::governance::{
+    next_action_id, read_queued_action, read_timelock_delay, remove_queued_action,
+    write_queued_action, write_timelock_delay,
+};
+use crate::escrow::{has_escrow, load_escrow, remove_escrow, write_escrow};
 use crate::event::{self};
-use crate::lease::{has_lease, load_lease, remove_lease, write_lease, 
-    get_all_listed, add_all_listed, remove_all_listed, 
-    add_leased_by_user, remove_leased_by_user, get_leased_by_user, 
+use crate::lease::{has_lease, load_lease, remove_lease, write_lease,
+    read_relist, remove_relist, write_relist,
+    add_listing, add_listings, remove_listing, remove_listings, move_listing, get_listings,
+    get_listing_count, add_leased_by_user, add_many_leased_by_user,
+    remove_many_leased_by_user, get_leased_by_user,
     add_rented_by_user, remove_rented_by_user, get_rented_by_user};
-use crate::storage_types::{LeaseState, Leasing, LeasingRenting, Renting, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT};
-use crate::token_utils::{balance, clawback, get_allowance, make_admin, mint, set_authorized, set_unauthorized, transfer_from};
+use crate::reservation::{
+    add_reservation, has_reservations, read_cancellation_policy, read_reservations,
+    remove_cancellation_policy, write_cancellation_policy, write_reservations,
+};
+use crate::migration::{read_lease_v1, remove_all_listed, remove_all_listed_if_empty, upgrade_lease};
+use crate::offer::{get_offers_by_renter, get_offers_by_token, has_offer, load_offer, remove_offer, write_offer};
+use crate::storage_types::{AdminAction, Collateral, EntryPoint, Escrow, LeaseEntry, LeaseState, Leasing, LeasingRenting, Offer, LeaseTerms, Payee, PriceTier, QueuedAction, RentQuote, RenterAccess, Renting, Role, Royalty, Reservation, CancellationPolicy, TimeUnit, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT};
+use crate::token_utils::{balance, clawback, get_allowance, make_admin, mint, read_admin, read_decimals, set_authorized, set_unauthorized, transfer, transfer_from};
 
-use soroban_sdk::{contractimpl, contract, Address, Env, BytesN, log, IntoVal, Vec};
+use soroban_sdk::{contractimpl, contract, vec, Address, Env, log, Vec};
 
 #[contract]
 pub struct SetLien;
 
+// Storage layout version, bumped whenever a stored type changes shape
+const CONTRACT_VERSION: u32 = 2;
 const NFT_BALANCE: i128 = 1;
+const SECONDS_IN_HOURS: u128 = 3600;
 const SECONDS_IN_DAYS: u128 = 86400;
+const SECONDS_IN_WEEKS: u128 = 7 * SECONDS_IN_DAYS;
+const MAX_BPS: u32 = 10_000;
+const MAX_PAYEES: u32 = 10;
+const MAX_BATCH_SIZE: u32 = 50;
+const MAX_BUNDLE_SIZE: u32 = 10;
+const MAX_PAGE_SIZE: u32 = 100;
+// Open offers are refunded when a listing closes, so they must fit in one call
+const MAX_OFFERS_PER_TOKEN: u32 = 20;
+const MAX_GRACE_PERIOD: u128 = 30 * SECONDS_IN_DAYS;
+const MAX_TIMELOCK_DELAY: u128 = 30 * SECONDS_IN_DAYS;
+// Keepers get at most 10% of the collateral or rent of a rental they settle
+const MAX_SETTLE_BOUNTY: u32 = 1_000;
 
 
 pub trait LienTrait {
-    fn initialize(env: Env, _admin: Address, _payment_token: Address);
+    fn initialize(env: Env, _admin: Address, _payment_token: Address) -> Result<(), LienError>;
+
+    fn propose_admin(env: Env, new_admin: Address);
+
+    fn accept_admin(env: Env) -> Result<(), LienError>;
+
+    fn grant_role(env: Env, role: Role, account: Address);
+
+    fn revoke_role(env: Env, role: Role);
+
+    fn queue_action(env: Env, action: AdminAction) -> Result<u32, LienError>;
+
+    fn execute_action(env: Env, id: u32) -> Result<(), LienError>;
+
+    fn cancel_action(env: Env, id: u32) -> Result<(), LienError>;
 
     fn pause(env: Env);
 
     fn resume(env: Env);
 
-    fn change_nft_admin(env: Env, token: Address, new_admin: Address);
+    fn set_pausable(env: Env, entry_point: EntryPoint, pausable: bool);
+
+    fn add_payment_token(env: Env, payment_token: Address);
+
+    fn remove_payment_token(env: Env, payment_token: Address);
+
+    fn approve_collection(env: Env, token: Address);
+
+    fn revoke_collection(env: Env, token: Address);
+
+    fn set_royalty(env: Env, token: Address, recipient: Address, royalty_bps: u32) -> Result<(), LienError>;
+
+    fn remove_royalty(env: Env, token: Address);
+
+    fn set_royalty_cap(env: Env, cap_bps: u32) -> Result<(), LienError>;
+
+    fn set_treasury(env: Env, treasury: Option<Address>);
+
+    fn withdraw_fees(env: Env, token: Address, to: Address) -> Result<u128, LienError>;
+
+    fn set_time_unit(env: Env, unit: TimeUnit);
+
+    fn set_settle_bounty(env: Env, bounty_bps: u32) -> Result<(), LienError>;
+
+    fn lease(env: Env, leaser: Address, token: Address, payment_token: Address, _price: u128, _duration: u128, terms: LeaseTerms) -> Result<(), LienError>;
+
+    fn update_lease(env: Env, leaser: Address, token: Address, price: u128, max_duration: u128) -> Result<(), LienError>;
+
+    fn lease_many(env: Env, leaser: Address, payment_token: Address, grace_period: u128, entries: Vec<LeaseEntry>) -> Result<(), LienError>;
+
+    fn lease_bundle(env: Env, leaser: Address, tokens: Vec<Address>, payment_token: Address, price: u128, duration: u128, grace_period: u128) -> Result<(), LienError>;
+
+    fn update_lease_many(env: Env, leaser: Address, entries: Vec<LeaseEntry>) -> Result<(), LienError>;
+
+    fn end_lease_many(env: Env, leaser: Address, tokens: Vec<Address>) -> Result<(), LienError>;
+
+    fn set_pricing(env: Env, leaser: Address, token: Address, tiers: Vec<PriceTier>, min_duration: u128) -> Result<(), LienError>;
+
+    fn set_renter_access(env: Env, leaser: Address, token: Address, access: Option<RenterAccess>) -> Result<(), LienError>;
+
+    fn add_allowed_renter(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError>;
+
+    fn remove_allowed_renter(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError>;
+
+    fn quote_rent(env: Env, token: Address, duration: u128) -> Result<RentQuote, LienError>;
 
-    fn change_payment_token(env: Env, payment_token: Address);
+    fn rent(env: Env, renter: Address, token: Address, duration: u128) -> Result<(), LienError>;
 
-    fn lease(env: Env, leaser: Address, token: Address, _price: u128, _duration: u128);
+    fn extend_rent(env: Env, renter: Address, token: Address, extra_duration: u128) -> Result<(), LienError>;
 
-    fn rent(env: Env, renter: Address, token: Address, duration: u128);
+    fn end_lease(env: Env, leaser: Address, token: Address) -> Result<(), LienError>;
 
-    fn end_lease(env: Env, leaser: Address, token: Address);
+    fn end_rent(env: Env, renter: Address, token: Address) -> Result<(), LienError>;
 
-    fn end_rent(env: Env, renter: Address, token: Address);
+    fn claim_token(env: Env, leaser: Address, token: Address, relist: bool) -> Result<(), LienError>;
 
-    fn claim_token(env: Env, leaser: Address, token: Address, relist: bool);
+    fn settle(env: Env, keeper: Address, token: Address) -> Result<u128, LienError>;
+
+    fn set_relist(env: Env, leaser: Address, token: Address, relist: bool) -> Result<(), LienError>;
+
+    fn withdraw_earnings(env: Env, token: Address) -> Result<u128, LienError>;
+
+    fn make_offer(env: Env, renter: Address, token: Address, price_per_unit: u128, duration: u128, expiry: u128) -> Result<(), LienError>;
+
+    fn accept_offer(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError>;
+
+    fn reject_offer(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError>;
+
+    fn cancel_offer(env: Env, renter: Address, token: Address) -> Result<(), LienError>;
+
+    fn reserve(env: Env, renter: Address, token: Address, start: u128, duration: u128) -> Result<(), LienError>;
+
+    fn activate_reservation(env: Env, token: Address) -> Result<(), LienError>;
+
+    fn cancel_reservation(env: Env, renter: Address, token: Address, start: u128) -> Result<u128, LienError>;
+
+    fn set_cancellation_policy(env: Env, leaser: Address, token: Address, notice: u128, penalty_bps: u32) -> Result<(), LienError>;
 
     fn has_lease(env: Env, token: Address) -> bool;
 
     fn get_lease(env: Env, token: Address) -> Option<LeasingRenting>;
 
+    fn get_escrow(env: Env, token: Address) -> Option<Escrow>;
+
+    fn claimable_at(env: Env, token: Address) -> Result<Option<u128>, LienError>;
+
+    fn get_earnings(env: Env, token: Address) -> u128;
+
     fn get_admin(env: Env) -> Address;
 
+    fn get_pending_admin(env: Env) -> Option<Address>;
+
+    fn get_queued_action(env: Env, id: u32) -> Option<QueuedAction>;
+
+    fn get_timelock_delay(env: Env) -> u128;
+
+    fn get_role(env: Env, role: Role) -> Address;
+
     fn get_payment_token(env: Env) -> Address;
 
+    fn get_payment_tokens(env: Env) -> Vec<Address>;
+
+    fn is_collection_approved(env: Env, token: Address) -> bool;
+
+    fn get_fee(env: Env) -> u32;
+
+    fn get_royalty(env: Env, token: Address) -> Option<Royalty>;
+
+    fn get_royalty_cap(env: Env) -> u32;
+
+    fn get_treasury(env: Env) -> Option<Address>;
+
+    fn get_accrued_fees(env: Env, token: Address) -> u128;
+
+    fn get_time_unit(env: Env) -> TimeUnit;
+
+    fn get_settle_bounty(env: Env) -> u32;
+
+    fn get_relist(env: Env, token: Address) -> bool;
+
+    fn is_paused(env: Env) -> bool;
+
+    fn is_pausable(env: Env, entry_point: EntryPoint) -> bool;
+
     fn get_all_listed(env: Env) -> Vec<Address>;
 
+    fn get_listings(env: Env, state: LeaseState, offset: u32, limit: u32) -> Result<Vec<Address>, LienError>;
+
+    fn get_listing_count(env: Env, state: LeaseState) -> Result<u32, LienError>;
+
     fn get_leased_by_user(env: Env, user: Address) -> Vec<Address>;
 
     fn get_rented_by_user(env: Env, user: Address) -> Vec<Address>;
 
-    fn upgrade(env: Env, new_wasm_hash: BytesN<32>);
+    fn get_offer(env: Env, token: Address, renter: Address) -> Option<Offer>;
+
+    fn get_offers_by_token(env: Env, token: Address) -> Vec<Address>;
+
+    fn get_offers_by_renter(env: Env, renter: Address) -> Vec<Address>;
+
+    fn get_reservations(env: Env, token: Address) -> Vec<Reservation>;
+
+    fn get_renter_access(env: Env, token: Address) -> Option<RenterAccess>;
+
+    fn get_bundle(env: Env, token: Address) -> Vec<Address>;
+
+    fn get_cancellation_policy(env: Env, token: Address) -> CancellationPolicy;
+
+    fn migrate(env: Env, tokens: Vec<Address>) -> Result<u32, LienError>;
+
+    fn version(env: Env) -> u32;
 
 }
 
 #[contractimpl]
 impl LienTrait for SetLien {
-    fn initialize(env: Env, _admin: Address, _payment_token: Address) {
+    fn initialize(env: Env, _admin: Address, _payment_token: Address) -> Result<(), LienError> {
         if has_administrator(&env) {
-            panic!("already initialized")
+            return Err(LienError::AlreadyInitialized);
         }
         write_administrator(&env, &_admin);
+        write_version(&env, CONTRACT_VERSION);
         write_payment_token(&env, &_payment_token);
+        add_payment_token(&env, &_payment_token);
 
         event::initialized(&env, &_admin, &_payment_token);
+        Ok(())
     }
 
-    fn pause(env: Env) {
+    fn propose_admin(env: Env, new_admin: Address) {
         let admin = read_administrator(&env);
         admin.require_auth();
 
-        pause_rent(&env);
-        event::paused(&env, admin);
+        write_pending_admin(&env, &Some(new_admin.clone()));
+        event::admin_proposed(&env, admin, &new_admin);
     }
 
-    fn resume(env: Env) {
-        let admin = read_administrator(&env);
-        admin.require_auth();
+    fn accept_admin(env: Env) -> Result<(), LienError> {
+        let new_admin = read_pending_admin(&env).ok_or(LienError::NoPendingAdmin)?;
+        new_admin.require_auth();
 
-        resume_rent(&env);
-        event::resumed(&env, admin);
+        let old_admin = read_administrator(&env);
+        write_administrator(&env, &new_admin);
+        write_pending_admin(&env, &None);
+
+        event::admin_changed(&env, &old_admin, &new_admin);
+        Ok(())
     }
 
-    fn change_nft_admin(env: Env, token: Address, new_admin: Address) {
+    fn grant_role(env: Env, role: Role, account: Address) {
         let admin = read_administrator(&env);
         admin.require_auth();
 
-        make_admin(&env, &token, &new_admin);
+        write_role(&env, role, &Some(account.clone()));
+        event::role_granted(&env, admin, role, &account);
     }
 
-    fn change_payment_token(env: Env, payment_token: Address) {
+    fn revoke_role(env: Env, role: Role) {
         let admin = read_administrator(&env);
         admin.require_auth();
 
-        write_payment_token(&env, &payment_token);
+        // The role goes back to the admin
+        let account = read_role(&env, role);
+        write_role(&env, role, &None);
+        event::role_revoked(&env, admin, role, account);
     }
 
-    fn lease(env: Env, leaser: Address, token: Address, _price: u128, _duration: u128) {
-        leaser.require_auth();
-        
+    fn queue_action(env: Env, action: AdminAction) -> Result<u32, LienError> {
+        let caller = read_action_role(&env, &action);
+        caller.require_auth();
+
         env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        
-        // Already has lease
-        if has_lease(&env, &token) {
-            panic!("token already has lease");
+
+        match action {
+            AdminAction::SetFee(fee_bps) if fee_bps > MAX_BPS => return Err(LienError::InvalidFee),
+            AdminAction::SetTimelockDelay(delay) if delay > MAX_TIMELOCK_DELAY => return Err(LienError::InvalidDelay),
+            _ => {}
         }
-        
-        if !is_leaseable(&env, &leaser, &token, _price, _duration) {
-            panic!("cannot lease token");
+
+        let eta = env.ledger().timestamp() as u128 + read_timelock_delay(&env);
+        let id = next_action_id(&env);
+        write_queued_action(&env, id, &QueuedAction { action: action.clone(), eta });
+
+        event::action_queued(&env, caller, id, action, eta);
+        Ok(id)
+    }
+
+    fn execute_action(env: Env, id: u32) -> Result<(), LienError> {
+        let queued = read_queued_action(&env, id).ok_or(LienError::ActionNotFound)?;
+        let caller = read_action_role(&env, &queued.action);
+        caller.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+
+        if (env.ledger().timestamp() as u128) < queued.eta {
+            return Err(LienError::ActionNotReady);
         }
-        
-        if balance(&env, &token, &leaser) == 0 {
-            panic!("not enough balance");
+
+        remove_queued_action(&env, id);
+
+        match queued.action {
+            AdminAction::Upgrade(new_wasm_hash) => {
+                env.deployer().update_current_contract_wasm(new_wasm_hash);
+            }
+            AdminAction::ChangeNftAdmin(token, new_admin) => {
+                make_admin(&env, &token, &new_admin);
+            }
+            AdminAction::ChangePaymentToken(payment_token) => {
+                write_payment_token(&env, &payment_token);
+                add_payment_token(&env, &payment_token);
+            }
+            AdminAction::SetFee(fee_bps) => {
+                write_fee_bps(&env, fee_bps);
+                event::fee_set(&env, caller.clone(), fee_bps);
+            }
+            AdminAction::SetTimelockDelay(delay) => {
+                // Applies to actions queued from now on
+                write_timelock_delay(&env, delay);
+            }
         }
 
-        // Set authorized to false so that user cannot transfer token unless delisted
-        set_unauthorized(&env, &token, &leaser);
-        // Set all fields
-        let lease = Leasing {
-            leaser: leaser.clone(),
-            max_duration: _duration,
-            price: _price,
-        };
-        let renting: Renting = Renting {
-            renter: leaser.clone(),
-            rent_duration: 0,
-            rented_at: 0,
-        };
+        event::action_executed(&env, caller, id);
+        Ok(())
+    }
 
-        let leaserent = LeasingRenting {
-            leasing: lease,
-            renting,
-            state: crate::storage_types::LeaseState::Listed,
-        };
+    fn cancel_action(env: Env, id: u32) -> Result<(), LienError> {
+        let queued = read_queued_action(&env, id).ok_or(LienError::ActionNotFound)?;
+        let caller = read_action_role(&env, &queued.action);
+        caller.require_auth();
 
-        // write lease
-        write_lease(&env, &token, &leaserent);
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
 
-        add_all_listed(&env, &token);
+        remove_queued_action(&env, id);
 
-        add_leased_by_user(&env, &leaser, &token);
+        event::action_cancelled(&env, caller, id);
+        Ok(())
+    }
+
+    fn pause(env: Env) {
+        let pauser = read_role(&env, Role::Pauser);
+        pauser.require_auth();
 
-        event::leased(&env, &leaser, &token, _price, _duration);
-        // Emit event
+        pause_rent(&env);
+        event::paused(&env, pauser);
     }
 
-    fn rent(env: Env, renter: Address, token: Address, duration: u128) {
-        // Transfer token to renter
-        // Set authorized to false so that user cannot transfer token
-        // Transfer payment to leaser
-        // Set all fields
+    fn resume(env: Env) {
+        let pauser = read_role(&env, Role::Pauser);
+        pauser.require_auth();
 
-        renter.require_auth();
+        resume_rent(&env);
+        event::resumed(&env, pauser);
+    }
 
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        if !has_lease(&env, &token) {
-            panic!("token does not have lease");
+    fn set_pausable(env: Env, entry_point: EntryPoint, pausable: bool) {
+        let pauser = read_role(&env, Role::Pauser);
+        pauser.require_auth();
+
+        write_pausable(&env, entry_point, pausable);
+        event::pausable_set(&env, pauser, entry_point, pausable);
+    }
+
+    fn add_payment_token(env: Env, payment_token: Address) {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
+
+        if add_payment_token(&env, &payment_token) {
+            event::payment_token_added(&env, manager, &payment_token);
         }
+    }
 
-        // Load lease
-        let mut leaser_renter = load_lease(&env, &token);
-        let leaser = &leaser_renter.leasing.leaser;
-        let price = calculate_total_price(duration, leaser_renter.leasing.price);
-        let payment_token = read_payment_token(&env);
+    fn remove_payment_token(env: Env, payment_token: Address) {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
 
-        if !is_rentable(
-            &env,
-            &renter,
-            &leaser,
-            duration,
-            leaser_renter.leasing.max_duration,
-        ) {
-            panic!("cannot rent token");
+        // Existing listings keep charging in the token they were listed with
+        if remove_payment_token(&env, &payment_token) {
+            event::payment_token_removed(&env, manager, &payment_token);
         }
+    }
 
-        // Transfer payment token to the leaser
-        transfer_from(
-            &env,
-            &payment_token,
-            &renter,
-            leaser,
-            price.try_into().unwrap(),
-        );
+    fn approve_collection(env: Env, token: Address) {
+        let manager = read_role(&env, Role::CollectionManager);
+        manager.require_auth();
 
-        // Authorize leaser to transfer nft to renter
-        set_authorized(&env, &token, &leaser);
+        approve_collection(&env, &token);
+        event::collection_approved(&env, manager, &token, true);
+    }
 
-        // Transfer nft to the renter
-        transfer_from(&env, &token, leaser, &renter, NFT_BALANCE);
-        // Set authorized to false so that user cannot transfer token unless delisted
-        set_unauthorized(&env, &token, &renter);
-        // Set all fields
-        let renting: Renting = Renting {
-            renter: renter.clone(),
-            rent_duration: duration,
-            rented_at: env.ledger().timestamp() as u128,
-        };
+    fn revoke_collection(env: Env, token: Address) {
+        let manager = read_role(&env, Role::CollectionManager);
+        manager.require_auth();
 
-        leaser_renter.renting = renting;
-        leaser_renter.state = LeaseState::Rented;
+        revoke_collection(&env, &token);
+        event::collection_approved(&env, manager, &token, false);
+    }
 
-        write_lease(&env, &token, &leaser_renter);
+    fn set_royalty(env: Env, token: Address, recipient: Address, royalty_bps: u32) -> Result<(), LienError> {
+        let manager = read_role(&env, Role::CollectionManager);
+        manager.require_auth();
 
-        add_rented_by_user(&env, &renter, &token);
+        if royalty_bps == 0 || royalty_bps > read_royalty_cap(&env) {
+            return Err(LienError::InvalidRoyalty);
+        }
 
-        event::rented(&env, &renter, &token,  duration);
+        let royalty = Royalty { recipient, bps: royalty_bps };
+        write_royalty(&env, &token, &royalty);
+        event::royalty_set(&env, manager, &token, Some(royalty));
+        Ok(())
     }
 
-    fn end_lease(env: Env, leaser: Address, token: Address) {
-        // Check lease status
-        // Set authorized to true
-        // Change admin back to leaser
+    fn remove_royalty(env: Env, token: Address) {
+        let manager = read_role(&env, Role::CollectionManager);
+        manager.require_auth();
 
-        leaser.require_auth();
+        remove_royalty(&env, &token);
+        event::royalty_set(&env, manager, &token, None);
+    }
 
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    fn set_royalty_cap(env: Env, cap_bps: u32) -> Result<(), LienError> {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
 
-        if !has_lease(&env, &token) {
-            panic!("token does not have lease");
+        if cap_bps > MAX_BPS {
+            return Err(LienError::InvalidRoyalty);
         }
-        // Load lease
-        let leaser_renter = load_lease(&env, &token);
 
-        if leaser_renter.state != LeaseState::Listed {
-            panic!("cannot end lease for a non-listed token");
+        write_royalty_cap(&env, cap_bps);
+        event::royalty_cap_set(&env, manager, cap_bps);
+        Ok(())
+    }
+
+    fn set_treasury(env: Env, treasury: Option<Address>) {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
+
+        write_treasury(&env, &treasury);
+        event::treasury_set(&env, manager, treasury);
+    }
+
+    fn withdraw_fees(env: Env, token: Address, to: Address) -> Result<u128, LienError> {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
+
+        let amount = read_accrued_fees(&env, &token);
+        if amount == 0 {
+            return Err(LienError::NothingToWithdraw);
         }
 
-        // Set authorized to true
-        set_authorized(&env, &token, &leaser);
+        write_accrued_fees(&env, &token, 0);
+        transfer(&env, &token, &to, to_amount(amount)?);
 
-        remove_lease(&env, &token);
+        event::fees_withdrawn(&env, &to, &token, amount);
+        Ok(amount)
+    }
 
-        remove_all_listed(&env, &token);
+    fn set_settle_bounty(env: Env, bounty_bps: u32) -> Result<(), LienError> {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
 
-        remove_leased_by_user(&env, &leaser, &token);
+        if bounty_bps > MAX_SETTLE_BOUNTY {
+            return Err(LienError::InvalidFee);
+        }
 
-        event::end_lease(&env, &leaser, &token, 0);
+        write_settle_bounty(&env, bounty_bps);
+        event::settle_bounty_set(&env, manager, bounty_bps);
+        Ok(())
     }
 
-    fn end_rent(env: Env, renter: Address, token: Address) {
-        // Check lease status
-        // Transfer token from renter to leaser
-        // Set authorized to true for both
+    fn set_time_unit(env: Env, unit: TimeUnit) {
+        let manager = read_role(&env, Role::FeeManager);
+        manager.require_auth();
 
-        renter.require_auth();
+        // Only applies to new listings, existing ones keep the unit they were listed with
+        write_time_unit(&env, unit);
+        event::time_unit_set(&env, manager, unit);
+    }
 
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    fn lease(env: Env, leaser: Address, token: Address, payment_token: Address, _price: u128, _duration: u128, terms: LeaseTerms) -> Result<(), LienError> {
+        leaser.require_auth();
 
-        if !has_lease(&env, &token) {
-            panic!("token does not have lease");
-        }
-        // Load lease
-        let leaser_renter = load_lease(&env, &token);
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
 
-        if leaser_renter.state != LeaseState::Rented {
-            panic!("cannot end rent for a non-rented token");
-        }
+        check_paused(&env, EntryPoint::Lease)?;
 
-        // Authorize renter to transfer nft to leaser
-        set_authorized(&env, &token, &renter);
+        create_lease(&env, &leaser, &token, payment_token, _price, _duration, terms)?;
 
-        // Transfer nft to the renter
-        transfer_from(
-            &env,
-            &token,
-            &renter,
-            &leaser_renter.leasing.leaser,
-            NFT_BALANCE,
-        );
+        add_listing(&env, LeaseState::Listed, &token);
 
-        set_authorized(&env, &token, &leaser_renter.leasing.leaser);
-        
-        remove_lease(&env, &token);
+        add_leased_by_user(&env, &leaser, &token);
+        Ok(())
+    }
 
-        remove_all_listed(&env, &token);
+    fn update_lease(env: Env, leaser: Address, token: Address, price: u128, max_duration: u128) -> Result<(), LienError> {
+        leaser.require_auth();
 
-        remove_rented_by_user(&env, &renter, &token);
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
 
-        remove_leased_by_user(&env, &leaser_renter.leasing.leaser, &token);
+        check_paused(&env, EntryPoint::UpdateLease)?;
 
-        event::returned(&env, &renter, &token, 0);
+        update_terms(&env, &leaser, &token, price, max_duration)
     }
 
-    fn claim_token(env: Env, leaser: Address, token: Address, relist: bool) {
+    fn lease_many(env: Env, leaser: Address, payment_token: Address, grace_period: u128, entries: Vec<LeaseEntry>) -> Result<(), LienError> {
         leaser.require_auth();
 
-        let current = &env.current_contract_address();
         env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
 
-        // Load lease
-        let mut leaser_renter: LeasingRenting = load_lease(&env, &token);
+        check_paused(&env, EntryPoint::Lease)?;
 
-        if leaser_renter.state != LeaseState::Rented {
-            panic!("cannot default for a non-rented token");
+        if entries.len() > MAX_BATCH_SIZE {
+            return Err(LienError::BatchTooLarge);
         }
 
-        let (duration, rented_at, max_duration) = (
-            leaser_renter.leasing.max_duration,
-            leaser_renter.renting.rented_at,
-            leaser_renter.renting.rent_duration,
-        );
+        // Any failing entry reverts the whole batch
+        let mut tokens = vec![&env];
+        for entry in entries.iter() {
+            create_lease(&env, &leaser, &entry.token, payment_token.clone(), entry.price, entry.duration, default_terms(&env, &payment_token, grace_period))?;
+            tokens.push_back(entry.token);
+        }
 
-        let renter = &leaser_renter.renting.renter;
-        let leaser = &leaser_renter.leasing.leaser;
+        add_listings(&env, LeaseState::Listed, &tokens);
 
-        // Check if rent is overdue
-        if !is_claimable(&env, rented_at, duration, max_duration) {
-            panic!("cannot claim token");
-        }
+        add_many_leased_by_user(&env, &leaser, &tokens);
+        Ok(())
+    }
 
-        // Authorize renter to transfer nft to leaser
-        set_authorized(&env, &token, renter);
+    fn lease_bundle(env: Env, leaser: Address, tokens: Vec<Address>, payment_token: Address, price: u128, duration: u128, grace_period: u128) -> Result<(), LienError> {
+        leaser.require_auth();
 
-        let allowance = get_allowance(&env, &token, renter, current);
-        // Check if there is no approval from renter, then clawback and mint
-        if allowance == 0 {
-            clawback(&env, &token, renter, &NFT_BALANCE);
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
 
-            mint(&env, &token, leaser, &NFT_BALANCE);
-        } else {
-            // Transfer nft to the renter
-            transfer_from(
-                &env,
-                &token,
-                renter,
-                leaser,
-                NFT_BALANCE,
-            );
+        check_paused(&env, EntryPoint::Lease)?;
+
+        if tokens.len() < 2 || tokens.len() > MAX_BUNDLE_SIZE {
+            return Err(LienError::InvalidBundle);
         }
 
-        if relist {
+        // The bundle is leased under its first token, which `create_lease` checks
+        let bundle = tokens.get_unchecked(0);
+        for (i, nft) in tokens.iter().enumerate() {
+            if tokens.first_index_of(nft.clone()) != Some(i as u32) {
+                return Err(LienError::InvalidBundle);
+            }
+            if i == 0 {
+                continue;
+            }
+            if has_lease(&env, &nft) || read_bundle_of(&env, &nft).is_some() {
+                return Err(LienError::LeaseAlreadyExists);
+            }
+
+            check_nft(&env, &leaser, &nft)?;
+
             // Set authorized to false so that user cannot transfer token unless delisted
-            set_unauthorized(&env, &token, leaser);
-            leaser_renter.state = LeaseState::Listed;
-            leaser_renter.renting.rent_duration = 0;
-            write_lease(&env, &token, &leaser_renter);
-        } else {
-            set_authorized(&env, &token, leaser);
-            
-            remove_lease(&env, &token);
+            set_unauthorized(&env, &nft, &leaser);
+        }
 
-            remove_all_listed(&env, &token);
+        let terms = default_terms(&env, &payment_token, grace_period);
+        create_lease(&env, &leaser, &bundle, payment_token, price, duration, terms)?;
+        write_bundle(&env, &bundle, &tokens);
 
-            remove_leased_by_user(&env, &leaser, &token);
-        }
+        add_listing(&env, LeaseState::Listed, &bundle);
 
-        remove_rented_by_user(&env, renter, &token);
+        add_many_leased_by_user(&env, &leaser, &tokens);
 
-        event::claimed(&env, &leaser, &token, relist);
+        event::bundle_leased(&env, &leaser, &bundle, tokens);
+        Ok(())
     }
-    
-    fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
-        let admin = read_administrator(&env);
-        admin.require_auth();
+
+    fn update_lease_many(env: Env, leaser: Address, entries: Vec<LeaseEntry>) -> Result<(), LienError> {
+        leaser.require_auth();
 
         env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
 
-        env.deployer().update_current_contract_wasm(new_wasm_hash);
-    }
+        check_paused(&env, EntryPoint::UpdateLease)?;
 
-    fn get_lease(env: Env, token: Address) -> Option<LeasingRenting> {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        if has_lease(&env, &token) {
-            Some(load_lease(&env, &token))
-        } else {
-            None
+        if entries.len() > MAX_BATCH_SIZE {
+            return Err(LienError::BatchTooLarge);
         }
-    }
 
-    fn get_all_listed(env: Env) -> Vec<Address> {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        get_all_listed(&env)
+        for entry in entries.iter() {
+            update_terms(&env, &leaser, &lease_key(&env, &entry.token), entry.price, entry.duration)?;
+        }
+        Ok(())
     }
 
-    fn get_leased_by_user(env: Env, user: Address) -> Vec<Address> {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        get_leased_by_user(&env, &user)
-    }
+    fn end_lease_many(env: Env, leaser: Address, tokens: Vec<Address>) -> Result<(), LienError> {
+        leaser.require_auth();
 
-    fn get_rented_by_user(env: Env, user: Address) -> Vec<Address> {
         env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        get_rented_by_user(&env, &user)
-    }
 
-    fn has_lease(env: Env, token: Address) -> bool {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        has_lease(&env, &token)
-    }
+        check_paused(&env, EntryPoint::EndLease)?;
 
-    fn get_admin(env: Env) -> Address {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        read_administrator(&env)
-    }
+        if tokens.len() > MAX_BATCH_SIZE {
+            return Err(LienError::BatchTooLarge);
+        }
 
-    fn get_payment_token(env: Env) -> Address {
-        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
-        read_payment_token(&env)
-    }
-}
+        // Resolve members up front, delisting a bundle forgets them. Members of
+        // the same bundle end its lease once.
+        let mut leases = vec![&env];
+        for token in tokens.iter() {
+            let token = lease_key(&env, &token);
+            if !leases.contains(&token) {
+                leases.push_back(token);
+            }
+        }
 
-fn is_nft(env: &Env, leaser: &Address, token: &Address) -> bool {
-    true
-}
+        let mut nfts = vec![&env];
+        for token in leases.iter() {
+            nfts.append(&lease_tokens(&env, &token));
+            delist(&env, &leaser, &token)?;
+        }
 
-fn is_leaseable(
+        remove_listings(&env, LeaseState::Listed, &leases);
+
+        remove_many_leased_by_user(&env, &leaser, &nfts);
+        Ok(())
+    }
+
+    fn set_pricing(env: Env, leaser: Address, token: Address, tiers: Vec<PriceTier>, min_duration: u128) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::UpdateLease)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let mut leaser_renter = load_lease(&env, &token);
+
+        check_leaser(&leaser_renter, &leaser)?;
+
+        let unit = unit_seconds(leaser_renter.leasing.unit);
+        if !min_duration.is_multiple_of(unit) || min_duration > leaser_renter.leasing.max_duration {
+            return Err(LienError::InvalidPricing);
+        }
+
+        // Tiers start at increasing whole-unit durations
+        let mut previous = 0;
+        for tier in tiers.iter() {
+            if tier.price == 0
+                || tier.min_duration <= previous
+                || !tier.min_duration.is_multiple_of(unit)
+            {
+                return Err(LienError::InvalidPricing);
+            }
+            previous = tier.min_duration;
+        }
+
+        // An active rental keeps the unit price it was agreed on
+        leaser_renter.leasing.pricing = tiers.clone();
+        leaser_renter.leasing.min_duration = min_duration;
+
+        write_lease(&env, &token, &leaser_renter);
+
+        event::pricing_set(&env, &leaser, &token, tiers, min_duration);
+        Ok(())
+    }
+
+    fn set_renter_access(env: Env, leaser: Address, token: Address, access: Option<RenterAccess>) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::UpdateLease)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        check_leaser(&load_lease(&env, &token), &leaser)?;
+
+        if !is_valid_access(&access) {
+            return Err(LienError::InvalidAccess);
+        }
+
+        // Only gates new rentals, an active one runs its course
+        write_renter_access(&env, &token, &access);
+
+        event::renter_access_set(&env, &leaser, &token, access);
+        Ok(())
+    }
+
+    fn add_allowed_renter(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::UpdateLease)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        check_leaser(&load_lease(&env, &token), &leaser)?;
+
+        // Adding a renter to a public listing makes it private
+        let renters = match read_renter_access(&env, &token) {
+            None | Some(RenterAccess::Public) => vec![&env, renter],
+            Some(RenterAccess::Allowlist(mut renters)) => {
+                if !renters.contains(&renter) {
+                    renters.push_back(renter);
+                }
+                renters
+            }
+            Some(RenterAccess::Holder(..)) => return Err(LienError::InvalidAccess),
+        };
+
+        let access = Some(RenterAccess::Allowlist(renters));
+        write_renter_access(&env, &token, &access);
+
+        event::renter_access_set(&env, &leaser, &token, access);
+        Ok(())
+    }
+
+    fn remove_allowed_renter(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::UpdateLease)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        check_leaser(&load_lease(&env, &token), &leaser)?;
+
+        let Some(RenterAccess::Allowlist(mut renters)) = read_renter_access(&env, &token) else {
+            return Err(LienError::InvalidAccess);
+        };
+
+        if let Some(i) = renters.first_index_of(renter) {
+            renters.remove_unchecked(i);
+        }
+        // The last renter cannot be removed, make the listing public instead
+        if renters.is_empty() {
+            return Err(LienError::InvalidAccess);
+        }
+
+        let access = Some(RenterAccess::Allowlist(renters));
+        write_renter_access(&env, &token, &access);
+
+        event::renter_access_set(&env, &leaser, &token, access);
+        Ok(())
+    }
+
+    fn quote_rent(env: Env, token: Address, duration: u128) -> Result<RentQuote, LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leasing = load_lease(&env, &token).leasing;
+
+        if duration == 0
+            || !duration.is_multiple_of(unit_seconds(leasing.unit))
+            || duration < leasing.min_duration
+            || duration > leasing.max_duration
+        {
+            return Err(LienError::NotRentable);
+        }
+
+        let price_per_unit = calculate_unit_price(&leasing, duration);
+        let price = calculate_total_price(duration, price_per_unit, leasing.unit)?;
+        Ok(RentQuote {
+            units: duration / unit_seconds(leasing.unit),
+            price_per_unit,
+            price,
+            fee: calculate_fee(&env, price),
+            collateral: leasing.collateral,
+        })
+    }
+
+    fn rent(env: Env, renter: Address, token: Address, duration: u128) -> Result<(), LienError> {
+        // Transfer token to renter
+        // Set authorized to false so that user cannot transfer token
+        // Transfer payment to leaser
+        // Set all fields
+
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Rent)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        // Load lease
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Listed {
+            return Err(LienError::NotListed);
+        }
+
+        let unit_price = calculate_unit_price(&leaser_renter.leasing, duration);
+        let price = calculate_total_price(duration, unit_price, leaser_renter.leasing.unit)?;
+        let payment_token = leaser_renter.leasing.payment_token.clone();
+
+        if !is_rentable(&env, &renter, &leaser_renter.leasing, duration) {
+            return Err(LienError::NotRentable);
+        }
+
+        check_renter_access(&env, &token, &renter)?;
+
+        check_available(&env, &token, env.ledger().timestamp() as u128, duration)?;
+
+        // Hold the payment and the security deposit in the contract
+        transfer_from(
+            &env,
+            &payment_token,
+            &renter,
+            &env.current_contract_address(),
+            to_amount(price)?,
+        );
+        let collateral = leaser_renter.leasing.collateral.clone();
+        if collateral.amount > 0 {
+            transfer_from(
+                &env,
+                &collateral.token,
+                &renter,
+                &env.current_contract_address(),
+                to_amount(collateral.amount)?,
+            );
+        }
+
+        let renting = Renting {
+            renter,
+            rent_duration: duration,
+            rented_at: env.ledger().timestamp() as u128,
+            price: unit_price,
+            max_duration: leaser_renter.leasing.max_duration,
+            collateral,
+        };
+        start_rental(&env, &token, leaser_renter, renting, price)?;
+        Ok(())
+    }
+
+    fn extend_rent(env: Env, renter: Address, token: Address, extra_duration: u128) -> Result<(), LienError> {
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Extend)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        // Load lease
+        let mut leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Rented {
+            return Err(LienError::NotRented);
+        }
+
+        check_renter(&leaser_renter, &renter)?;
+
+        let rent_duration = leaser_renter.renting.rent_duration.checked_add(extra_duration).ok_or(LienError::NotRentable)?;
+        if extra_duration == 0
+            || !extra_duration.is_multiple_of(unit_seconds(leaser_renter.leasing.unit))
+            || rent_duration > leaser_renter.renting.max_duration
+        {
+            return Err(LienError::NotRentable);
+        }
+
+        check_available(&env, &token, leaser_renter.renting.rented_at, rent_duration)?;
+
+        let price = calculate_total_price(extra_duration, leaser_renter.renting.price, leaser_renter.leasing.unit)?;
+        let mut escrow = load_escrow(&env, &token);
+
+        transfer_from(
+            &env,
+            &escrow.payment_token,
+            &renter,
+            &env.current_contract_address(),
+            to_amount(price)?,
+        );
+        let fee = charge_fee(&env, &escrow.payment_token, price)?;
+        escrow.amount = escrow.amount.checked_add(price - fee).ok_or(LienError::AmountOverflow)?;
+        escrow.fee = escrow.fee.checked_add(fee).ok_or(LienError::AmountOverflow)?;
+        write_escrow(&env, &token, &escrow);
+
+        leaser_renter.renting.rent_duration = rent_duration;
+        write_lease(&env, &token, &leaser_renter);
+
+        event::escrowed(&env, &renter, &token, price - fee);
+        event::extended(&env, &renter, &token, extra_duration, price, fee);
+        Ok(())
+    }
+
+    fn end_lease(env: Env, leaser: Address, token: Address) -> Result<(), LienError> {
+        // Check lease status
+        // Set authorized to true
+        // Change admin back to leaser
+
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::EndLease)?;
+
+        let nfts = lease_tokens(&env, &token);
+        delist(&env, &leaser, &token)?;
+
+        remove_listing(&env, &token);
+
+        remove_many_leased_by_user(&env, &leaser, &nfts);
+        Ok(())
+    }
+
+    fn end_rent(env: Env, renter: Address, token: Address) -> Result<(), LienError> {
+        // Check lease status
+        // Transfer token from renter to leaser
+        // Set authorized to true for both
+
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::EndRent)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+        // Load lease
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Rented {
+            return Err(LienError::NotRented);
+        }
+
+        check_renter(&leaser_renter, &renter)?;
+
+        let nfts = lease_tokens(&env, &token);
+        for nft in nfts.iter() {
+            // Authorize renter to transfer nft to leaser
+            set_authorized(&env, &nft, &renter);
+
+            // Transfer nft to the renter
+            transfer_from(
+                &env,
+                &nft,
+                &renter,
+                &leaser_renter.leasing.leaser,
+                NFT_BALANCE,
+            );
+
+            set_authorized(&env, &nft, &leaser_renter.leasing.leaser);
+        }
+
+        // Pay the leaser for the days used and refund the rest
+        release_escrow(&env, &token, &leaser_renter)?;
+
+        let collateral = &leaser_renter.renting.collateral;
+        if collateral.amount > 0 {
+            transfer(&env, &collateral.token, &renter, to_amount(collateral.amount)?);
+            event::collateral_returned(&env, &renter, &token, collateral);
+        }
+
+        if has_reservations(&env, &token) {
+            // Keep the listing up for the bookings still ahead
+            let mut leaser_renter = leaser_renter;
+            for nft in nfts.iter() {
+                set_unauthorized(&env, &nft, &leaser_renter.leasing.leaser);
+            }
+            leaser_renter.state = LeaseState::Listed;
+            leaser_renter.renting.rent_duration = 0;
+            leaser_renter.renting.collateral.amount = 0;
+            write_lease(&env, &token, &leaser_renter);
+
+            move_listing(&env, LeaseState::Listed, &token);
+        } else {
+            close_listing(&env, &token, &leaser_renter.leasing.leaser)?;
+        }
+
+        for nft in nfts.iter() {
+            remove_rented_by_user(&env, &renter, &nft);
+        }
+
+        event::returned(&env, &renter, &token, 0);
+        Ok(())
+    }
+
+    fn claim_token(env: Env, leaser: Address, token: Address, relist: bool) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Claim)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+        // Load lease
+        let leaser_renter: LeasingRenting = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Rented {
+            return Err(LienError::NotRented);
+        }
+
+        check_leaser(&leaser_renter, &leaser)?;
+
+        reclaim(&env, &token, leaser_renter, relist, None)?;
+        Ok(())
+    }
+
+    fn settle(env: Env, keeper: Address, token: Address) -> Result<u128, LienError> {
+        keeper.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Claim)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+        let leaser_renter: LeasingRenting = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Rented {
+            return Err(LienError::NotRented);
+        }
+
+        // The parties would only be paying themselves the bounty
+        if keeper == leaser_renter.leasing.leaser || keeper == leaser_renter.renting.renter {
+            return Err(LienError::InvalidKeeper);
+        }
+
+        // Anyone can return an overdue rental, as the leaser would have
+        let relist = read_relist(&env, &token);
+        let bounty = reclaim(&env, &token, leaser_renter, relist, Some(&keeper))?;
+
+        event::settled(&env, &keeper, &token, bounty);
+        Ok(bounty)
+    }
+
+    fn set_relist(env: Env, leaser: Address, token: Address, relist: bool) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+        let leaser_renter = load_lease(&env, &token);
+        check_leaser(&leaser_renter, &leaser)?;
+
+        write_relist(&env, &token, relist);
+
+        event::relist_set(&env, &leaser, &token, relist);
+        Ok(())
+    }
+
+    fn withdraw_earnings(env: Env, token: Address) -> Result<u128, LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Withdraw)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+        let leaser_renter = load_lease(&env, &token);
+        let leaser = &leaser_renter.leasing.leaser;
+        leaser.require_auth();
+
+        if leaser_renter.state != LeaseState::Rented || !has_escrow(&env, &token) {
+            return Err(LienError::NotRented);
+        }
+
+        let mut escrow = load_escrow(&env, &token);
+        let amount = calculate_earned(&env, &leaser_renter, &escrow) - escrow.withdrawn;
+        if amount == 0 {
+            return Err(LienError::NothingToWithdraw);
+        }
+
+        pay_rent(&env, &token, &leaser_renter.leasing, &escrow.payment_token, amount)?;
+        escrow.withdrawn += amount;
+        write_escrow(&env, &token, &escrow);
+
+        event::withdrawn(&env, leaser, &token, amount);
+        Ok(amount)
+    }
+
+    fn make_offer(env: Env, renter: Address, token: Address, price_per_unit: u128, duration: u128, expiry: u128) -> Result<(), LienError> {
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Offer)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Listed {
+            return Err(LienError::NotListed);
+        }
+
+        if has_offer(&env, &token, &renter) {
+            return Err(LienError::OfferExists);
+        }
+        if get_offers_by_token(&env, &token).len() >= MAX_OFFERS_PER_TOKEN {
+            return Err(LienError::TooManyOffers);
+        }
+
+        check_renter_access(&env, &token, &renter)?;
+
+        if price_per_unit == 0
+            || !is_rentable(&env, &renter, &leaser_renter.leasing, duration)
+        {
+            return Err(LienError::NotRentable);
+        }
+
+        if expiry <= env.ledger().timestamp() as u128 {
+            return Err(LienError::OfferExpired);
+        }
+
+        let amount = calculate_total_price(duration, price_per_unit, leaser_renter.leasing.unit)?;
+        let payment_token = leaser_renter.leasing.payment_token.clone();
+        let collateral = leaser_renter.leasing.collateral.clone();
+
+        // Hold the offered payment and the security deposit until the offer is settled
+        transfer_from(
+            &env,
+            &payment_token,
+            &renter,
+            &env.current_contract_address(),
+            to_amount(amount)?,
+        );
+        if collateral.amount > 0 {
+            transfer_from(
+                &env,
+                &collateral.token,
+                &renter,
+                &env.current_contract_address(),
+                to_amount(collateral.amount)?,
+            );
+        }
+
+        write_offer(&env, &token, &Offer {
+            renter: renter.clone(),
+            payment_token,
+            price: price_per_unit,
+            duration,
+            expiry,
+            amount,
+            collateral,
+        });
+
+        event::offer_made(&env, &renter, &token, price_per_unit, duration, expiry);
+        Ok(())
+    }
+
+    fn accept_offer(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Rent)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Listed {
+            return Err(LienError::NotListed);
+        }
+
+        check_leaser(&leaser_renter, &leaser)?;
+
+        if !has_offer(&env, &token, &renter) {
+            return Err(LienError::OfferNotFound);
+        }
+
+        let offer = load_offer(&env, &token, &renter);
+        if offer.expiry <= env.ledger().timestamp() as u128 {
+            return Err(LienError::OfferExpired);
+        }
+        // The escrow is opened in the listing's tokens, which must be what the offer holds
+        if offer.payment_token != leaser_renter.leasing.payment_token
+            || offer.collateral.token != leaser_renter.leasing.collateral.token
+        {
+            return Err(LienError::PaymentTokenMismatch);
+        }
+
+        check_available(&env, &token, env.ledger().timestamp() as u128, offer.duration)?;
+
+        remove_offer(&env, &token, &renter);
+
+        // The offer already holds the payment and the collateral
+        let renting = Renting {
+            renter: renter.clone(),
+            rent_duration: offer.duration,
+            rented_at: env.ledger().timestamp() as u128,
+            price: offer.price,
+            max_duration: leaser_renter.leasing.max_duration,
+            collateral: offer.collateral,
+        };
+        start_rental(&env, &token, leaser_renter, renting, offer.amount)?;
+
+        event::offer_accepted(&env, &leaser, &token, &renter);
+        Ok(())
+    }
+
+    fn reject_offer(env: Env, leaser: Address, token: Address, renter: Address) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leaser_renter = load_lease(&env, &token);
+        check_leaser(&leaser_renter, &leaser)?;
+
+        if !has_offer(&env, &token, &renter) {
+            return Err(LienError::OfferNotFound);
+        }
+
+        refund_offer(&env, &token, &load_offer(&env, &token, &renter))?;
+
+        event::offer_rejected(&env, &leaser, &token, &renter);
+        Ok(())
+    }
+
+    fn cancel_offer(env: Env, renter: Address, token: Address) -> Result<(), LienError> {
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        if !has_offer(&env, &token, &renter) {
+            return Err(LienError::OfferNotFound);
+        }
+
+        refund_offer(&env, &token, &load_offer(&env, &token, &renter))?;
+
+        event::offer_cancelled(&env, &renter, &token);
+        Ok(())
+    }
+
+    fn reserve(env: Env, renter: Address, token: Address, start: u128, duration: u128) -> Result<(), LienError> {
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Reserve)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state == LeaseState::Available {
+            return Err(LienError::NotListed);
+        }
+
+        check_renter_access(&env, &token, &renter)?;
+
+        if start < env.ledger().timestamp() as u128
+            || !is_rentable(&env, &renter, &leaser_renter.leasing, duration)
+        {
+            return Err(LienError::NotRentable);
+        }
+
+        // The booking cannot start before the current rental is over
+        if leaser_renter.state == LeaseState::Rented && start < rental_end(&leaser_renter)? {
+            return Err(LienError::ReservationConflict);
+        }
+        check_available(&env, &token, start, duration)?;
+
+        let unit_price = calculate_unit_price(&leaser_renter.leasing, duration);
+        let amount = calculate_total_price(duration, unit_price, leaser_renter.leasing.unit)?;
+        let collateral = leaser_renter.leasing.collateral.clone();
+
+        // Prepay the booking and the security deposit
+        transfer_from(
+            &env,
+            &leaser_renter.leasing.payment_token,
+            &renter,
+            &env.current_contract_address(),
+            to_amount(amount)?,
+        );
+        if collateral.amount > 0 {
+            transfer_from(
+                &env,
+                &collateral.token,
+                &renter,
+                &env.current_contract_address(),
+                to_amount(collateral.amount)?,
+            );
+        }
+
+        add_reservation(&env, &token, &Reservation {
+            renter: renter.clone(),
+            payment_token: leaser_renter.leasing.payment_token.clone(),
+            start,
+            duration,
+            price: unit_price,
+            amount,
+            collateral,
+        });
+
+        event::reserved(&env, &renter, &token, start, duration, amount);
+        Ok(())
+    }
+
+    fn activate_reservation(env: Env, token: Address) -> Result<(), LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        check_paused(&env, EntryPoint::Rent)?;
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        let leaser_renter = load_lease(&env, &token);
+
+        if leaser_renter.state != LeaseState::Listed {
+            return Err(LienError::NotListed);
+        }
+
+        let now = env.ledger().timestamp() as u128;
+        let mut reservations = read_reservations(&env, &token);
+        let mut index = None;
+        for (i, reservation) in reservations.iter().enumerate() {
+            if reservation.start <= now && now < reservation_end(&reservation)? {
+                index = Some(i as u32);
+                break;
+            }
+        }
+
+        let Some(index) = index else {
+            return match reservations.first() {
+                Some(first) if first.start > now => Err(LienError::ReservationNotStarted),
+                Some(_) => Err(LienError::ReservationExpired),
+                None => Err(LienError::ReservationNotFound),
+            };
+        };
+
+        let reservation = reservations.get_unchecked(index);
+        reservations.remove_unchecked(index);
+        write_reservations(&env, &token, &reservations);
+
+        // The booked window is billed from its start, not from activation
+        let renting = Renting {
+            renter: reservation.renter.clone(),
+            rent_duration: reservation.duration,
+            rented_at: reservation.start,
+            price: reservation.price,
+            max_duration: leaser_renter.leasing.max_duration,
+            collateral: reservation.collateral,
+        };
+        start_rental(&env, &token, leaser_renter, renting, reservation.amount)?;
+
+        event::reservation_activated(&env, &reservation.renter, &token, reservation.start);
+        Ok(())
+    }
+
+    fn cancel_reservation(env: Env, renter: Address, token: Address, start: u128) -> Result<u128, LienError> {
+        renter.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        let mut reservations = read_reservations(&env, &token);
+        let Some(index) = reservations.iter().position(|r| r.start == start) else {
+            return Err(LienError::ReservationNotFound);
+        };
+
+        let reservation = reservations.get_unchecked(index as u32);
+        if reservation.renter != renter {
+            return Err(LienError::NotRenter);
+        }
+
+        reservations.remove_unchecked(index as u32);
+        write_reservations(&env, &token, &reservations);
+
+        // Late cancellations pay the leaser a penalty. A window that already
+        // started without being handed over is refunded in full.
+        let now = env.ledger().timestamp() as u128;
+        let policy = read_cancellation_policy(&env, &token);
+        let penalty = if now < start && start - now < policy.notice {
+            mul_div(reservation.amount, policy.penalty_bps as u128, MAX_BPS as u128)
+        } else {
+            0
+        };
+
+        if penalty > 0 {
+            let leasing = load_lease(&env, &token).leasing;
+            pay_leaser(&env, &token, &leasing, &reservation.payment_token, penalty)?;
+        }
+        refund_reservation(&env, &token, &reservation, reservation.amount - penalty)?;
+
+        event::reservation_cancelled(&env, &renter, &token, start, penalty);
+        Ok(penalty)
+    }
+
+    fn set_cancellation_policy(env: Env, leaser: Address, token: Address, notice: u128, penalty_bps: u32) -> Result<(), LienError> {
+        leaser.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+
+        if !has_lease(&env, &token) {
+            return Err(LienError::LeaseNotFound);
+        }
+
+        check_leaser(&load_lease(&env, &token), &leaser)?;
+
+        if penalty_bps > MAX_BPS {
+            return Err(LienError::InvalidPolicy);
+        }
+
+        write_cancellation_policy(&env, &token, &CancellationPolicy { notice, penalty_bps });
+
+        event::cancellation_policy_set(&env, &leaser, &token, notice, penalty_bps);
+        Ok(())
+    }
+
+    fn migrate(env: Env, tokens: Vec<Address>) -> Result<u32, LienError> {
+        let admin = read_administrator(&env);
+        admin.require_auth();
+
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+
+        if tokens.len() > MAX_BATCH_SIZE {
+            return Err(LienError::BatchTooLarge);
+        }
+
+        let payment_token = read_payment_token(&env);
+        // Version 1 only had the one payment token, without an allowlist
+        if read_version(&env) < CONTRACT_VERSION && read_payment_tokens(&env).is_empty() {
+            add_payment_token(&env, &payment_token);
+        }
+
+        let mut migrated = 0;
+        for token in tokens.iter() {
+            // Leases already in the current layout are left alone, so batches can be retried
+            let Some(old) = read_lease_v1(&env, &token) else {
+                continue;
+            };
+            let lease = upgrade_lease(&env, old, &payment_token);
+            write_lease(&env, &token, &lease);
+
+            remove_all_listed(&env, &token);
+            if lease.state != LeaseState::Available {
+                add_listing(&env, lease.state, &token);
+            }
+            migrated += 1;
+        }
+
+        // Only bump the version after the batch that migrates the last old lease
+        if remove_all_listed_if_empty(&env) {
+            write_version(&env, CONTRACT_VERSION);
+        }
+        event::migrated(&env, admin, read_version(&env), migrated);
+        Ok(migrated)
+    }
+
+    fn version(env: Env) -> u32 {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_version(&env)
+    }
+
+    fn get_lease(env: Env, token: Address) -> Option<LeasingRenting> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        if has_lease(&env, &token) {
+            Some(load_lease(&env, &token))
+        } else {
+            None
+        }
+    }
+
+    fn claimable_at(env: Env, token: Address) -> Result<Option<u128>, LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        if !has_lease(&env, &token) {
+            return Ok(None);
+        }
+        let leaser_renter = load_lease(&env, &token);
+        if leaser_renter.state != LeaseState::Rented {
+            return Ok(None);
+        }
+        Ok(Some(calculate_claimable_at(&leaser_renter)?))
+    }
+
+    fn get_escrow(env: Env, token: Address) -> Option<Escrow> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        if has_escrow(&env, &token) {
+            Some(load_escrow(&env, &token))
+        } else {
+            None
+        }
+    }
+
+    fn get_earnings(env: Env, token: Address) -> u128 {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        if !has_lease(&env, &token) || !has_escrow(&env, &token) {
+            return 0;
+        }
+        let escrow = load_escrow(&env, &token);
+        calculate_earned(&env, &load_lease(&env, &token), &escrow) - escrow.withdrawn
+    }
+
+    fn get_all_listed(env: Env) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        get_listings(&env, LeaseState::Listed, 0, get_listing_count(&env, LeaseState::Listed))
+    }
+
+    fn get_listings(env: Env, state: LeaseState, offset: u32, limit: u32) -> Result<Vec<Address>, LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        check_indexed(&state)?;
+        Ok(get_listings(&env, state, offset, limit.min(MAX_PAGE_SIZE)))
+    }
+
+    fn get_listing_count(env: Env, state: LeaseState) -> Result<u32, LienError> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        check_indexed(&state)?;
+        Ok(get_listing_count(&env, state))
+    }
+
+    fn get_leased_by_user(env: Env, user: Address) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        get_leased_by_user(&env, &user)
+    }
+
+    fn get_rented_by_user(env: Env, user: Address) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        get_rented_by_user(&env, &user)
+    }
+
+    fn get_offer(env: Env, token: Address, renter: Address) -> Option<Offer> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        if has_offer(&env, &token, &renter) {
+            Some(load_offer(&env, &token, &renter))
+        } else {
+            None
+        }
+    }
+
+    fn get_offers_by_token(env: Env, token: Address) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        get_offers_by_token(&env, &token)
+    }
+
+    fn get_offers_by_renter(env: Env, renter: Address) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        get_offers_by_renter(&env, &renter)
+    }
+
+    fn get_bundle(env: Env, token: Address) -> Vec<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        match read_bundle_of(&env, &token) {
+            Some(bundle) => read_bundle(&env, &bundle).unwrap(),
+            None => vec![&env],
+        }
+    }
+
+    fn get_renter_access(env: Env, token: Address) -> Option<RenterAccess> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        read_renter_access(&env, &token)
+    }
+
+    fn get_reservations(env: Env, token: Address) -> Vec<Reservation> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        read_reservations(&env, &token)
+    }
+
+    fn get_cancellation_policy(env: Env, token: Address) -> CancellationPolicy {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        read_cancellation_policy(&env, &token)
+    }
+
+    fn has_lease(env: Env, token: Address) -> bool {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        has_lease(&env, &token)
+    }
+
+    fn get_admin(env: Env) -> Address {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_administrator(&env)
+    }
+
+    fn get_pending_admin(env: Env) -> Option<Address> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_pending_admin(&env)
+    }
+
+    fn get_queued_action(env: Env, id: u32) -> Option<QueuedAction> {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_queued_action(&env, id)
+    }
+
+    fn get_timelock_delay(env: Env) -> u128 {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_timelock_delay(&env)
+    }
+
+    fn get_role(env: Env, role: Role) -> Address {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_role(&env, role)
+    }
+
+    fn get_payment_token(env: Env) -> Address {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_payment_token(&env)
+    }
+
+    fn get_payment_tokens(env: Env) -> Vec<Address> {
+        read_payment_tokens(&env)
+    }
+
+    fn is_collection_approved(env: Env, token: Address) -> bool {
+        is_collection_approved(&env, &token)
+    }
+
+    fn get_royalty(env: Env, token: Address) -> Option<Royalty> {
+        read_royalty(&env, &token)
+    }
+
+    fn get_royalty_cap(env: Env) -> u32 {
+        read_royalty_cap(&env)
+    }
+
+    fn get_fee(env: Env) -> u32 {
+        read_fee_bps(&env)
+    }
+
+    fn get_treasury(env: Env) -> Option<Address> {
+        read_treasury(&env)
+    }
+
+    fn get_accrued_fees(env: Env, token: Address) -> u128 {
+        read_accrued_fees(&env, &token)
+    }
+
+    fn get_settle_bounty(env: Env) -> u32 {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        read_settle_bounty(&env)
+    }
+
+    fn get_relist(env: Env, token: Address) -> bool {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+        let token = lease_key(&env, &token);
+        read_relist(&env, &token)
+    }
+
+    fn get_time_unit(env: Env) -> TimeUnit {
+        read_time_unit(&env)
+    }
+
+    fn is_paused(env: Env) -> bool {
+        is_paused(&env)
+    }
+
+    fn is_pausable(env: Env, entry_point: EntryPoint) -> bool {
+        read_pausable(&env, entry_point)
+    }
+}
+
+// Terms of batch and bundle listings, which only set a grace period
+fn default_terms(env: &Env, payment_token: &Address, grace_period: u128) -> LeaseTerms {
+    LeaseTerms {
+        grace_period,
+        collateral: Collateral {
+            token: payment_token.clone(),
+            amount: 0,
+        },
+        access: RenterAccess::Public,
+        payees: vec![env],
+    }
+}
+
+// Validates and writes a new listing, leaving the listing indexes to the caller
+fn create_lease(
     env: &Env,
     leaser: &Address,
     token: &Address,
+    payment_token: Address,
     _price: u128,
     _duration: u128,
-) -> bool {
-    if _price <= 0 || _duration <= 0 {
-        return false;
+    terms: LeaseTerms,
+) -> Result<(), LienError> {
+    let LeaseTerms { grace_period, collateral, access, payees } = terms;
+
+    // Already has lease, on its own or in a bundle
+    if has_lease(env, token) || read_bundle_of(env, token).is_some() {
+        return Err(LienError::LeaseAlreadyExists);
     }
 
-    if !is_nft(env, leaser, token) {
-        return false;
+    if !is_leaseable(_price, _duration) {
+        return Err(LienError::NotLeaseable);
     }
 
-    true
+    if !is_payment_token_accepted(env, &payment_token) {
+        return Err(LienError::PaymentTokenNotAccepted);
+    }
+
+    if grace_period > MAX_GRACE_PERIOD {
+        return Err(LienError::InvalidGracePeriod);
+    }
+
+    if collateral.amount > 0 {
+        if !is_payment_token_accepted(env, &collateral.token) {
+            return Err(LienError::PaymentTokenNotAccepted);
+        }
+        to_amount(collateral.amount)?;
+    }
+    let access = match access {
+        RenterAccess::Public => None,
+        access => Some(access),
+    };
+    if !is_valid_access(&access) {
+        return Err(LienError::InvalidAccess);
+    }
+    if !is_valid_payees(&payees) {
+        return Err(LienError::InvalidPayees);
+    }
+    // No collateral is kept in the payment token
+    let collateral = if collateral.amount == 0 {
+        Collateral {
+            token: payment_token.clone(),
+            amount: 0,
+        }
+    } else {
+        collateral
+    };
+    // Without payees the leaser receives everything
+    let payees = if payees.is_empty() {
+        vec![env, Payee { address: leaser.clone(), bps: MAX_BPS }]
+    } else {
+        payees
+    };
+
+    check_nft(env, leaser, token)?;
+
+    // Set authorized to false so that user cannot transfer token unless delisted
+    set_unauthorized(env, token, leaser);
+    // Set all fields
+    let lease = Leasing {
+        leaser: leaser.clone(),
+        payment_token,
+        max_duration: _duration,
+        price: _price,
+        grace_period,
+        collateral: collateral.clone(),
+        pricing: vec![env],
+        min_duration: 0,
+        unit: read_time_unit(env),
+        payees,
+    };
+    let renting: Renting = Renting {
+        renter: leaser.clone(),
+        rent_duration: 0,
+        rented_at: 0,
+        price: 0,
+        max_duration: 0,
+        collateral: Collateral {
+            token: collateral.token.clone(),
+            amount: 0,
+        },
+    };
+
+    let leaserent = LeasingRenting {
+        leasing: lease,
+        renting,
+        state: crate::storage_types::LeaseState::Listed,
+    };
+
+    // write lease
+    write_lease(env, token, &leaserent);
+
+    write_renter_access(env, token, &access);
+    if access.is_some() {
+        event::renter_access_set(env, leaser, token, access);
+    }
+
+    event::leased(env, leaser, token, _price, _duration);
+    Ok(())
+}
+
+// Hands the nft to the renter once the payment and collateral are held by the
+// contract. `price` is the total paid for `renting`.
+fn start_rental(env: &Env, token: &Address, mut leaser_renter: LeasingRenting, renting: Renting, price: u128) -> Result<(), LienError> {
+    let renter = &renting.renter;
+    let duration = renting.rent_duration;
+    let leaser = leaser_renter.leasing.leaser.clone();
+    let payment_token = leaser_renter.leasing.payment_token.clone();
+
+    // Protocol fee is taken up front and is not refundable
+    let fee = charge_fee(env, &payment_token, price)?;
+    write_escrow(env, token, &Escrow {
+        payment_token,
+        amount: price - fee,
+        withdrawn: 0,
+        fee,
+    });
+
+    if renting.collateral.amount > 0 {
+        event::collateral_locked(env, renter, token, &renting.collateral);
+    }
+
+    for nft in lease_tokens(env, token).iter() {
+        // Authorize leaser to transfer nft to renter
+        set_authorized(env, &nft, &leaser);
+
+        // Transfer nft to the renter
+        transfer_from(env, &nft, &leaser, renter, NFT_BALANCE);
+        // Set authorized to false so that user cannot transfer token unless delisted
+        set_unauthorized(env, &nft, renter);
+
+        add_rented_by_user(env, renter, &nft);
+    }
+    let renter = renting.renter.clone();
+    leaser_renter.renting = renting;
+    leaser_renter.state = LeaseState::Rented;
+
+    write_lease(env, token, &leaser_renter);
+
+    move_listing(env, LeaseState::Rented, token);
+
+    event::escrowed(env, &renter, token, price - fee);
+    event::rented(env, &renter, token, duration, price, fee);
+    Ok(())
+}
+
+// Returns an overdue rental to the leaser, relisting it or closing the listing.
+// With a `keeper` the caller settled on the leaser's behalf and is paid the
+// keeper bounty, which is returned.
+fn reclaim(env: &Env, token: &Address, mut leaser_renter: LeasingRenting, relist: bool, keeper: Option<&Address>) -> Result<u128, LienError> {
+    let current = &env.current_contract_address();
+    let (duration, rented_at, grace_period) = (
+        leaser_renter.renting.rent_duration,
+        leaser_renter.renting.rented_at,
+        leaser_renter.leasing.grace_period,
+    );
+
+    let renter = &leaser_renter.renting.renter;
+    let leaser = &leaser_renter.leasing.leaser;
+
+    // Check if rent is overdue
+    if !is_claimable(env, rented_at, duration, grace_period)? {
+        return Err(LienError::NotClaimable);
+    }
+
+    let nfts = lease_tokens(env, token);
+    for nft in nfts.iter() {
+        // Authorize renter to transfer nft to leaser
+        set_authorized(env, &nft, renter);
+
+        let allowance = get_allowance(env, &nft, renter, current);
+        // Check if there is no approval from renter, then clawback and mint
+        if allowance == 0 {
+            clawback(env, &nft, renter, &NFT_BALANCE);
+
+            mint(env, &nft, leaser, &NFT_BALANCE);
+        } else {
+            // Transfer nft to the renter
+            transfer_from(
+                env,
+                &nft,
+                renter,
+                leaser,
+                NFT_BALANCE,
+            );
+        }
+    }
+
+    // Rent held for the rental and the fee it paid, which fund the bounty when
+    // there is no collateral
+    let (payment_token, rent, fee) = if has_escrow(env, token) {
+        let escrow = load_escrow(env, token);
+        (escrow.payment_token, escrow.amount, escrow.fee)
+    } else {
+        (leaser_renter.leasing.payment_token.clone(), 0, 0)
+    };
+    release_escrow(env, token, &leaser_renter)?;
+
+    // The renter defaulted, so the deposit goes to the leaser, less the bounty
+    // of whoever settled the rental
+    let mut collateral = leaser_renter.renting.collateral.clone();
+    let bounty = match keeper {
+        Some(keeper) => pay_bounty(env, keeper, &mut collateral, &payment_token, rent, fee)?,
+        None => 0,
+    };
+    if collateral.amount > 0 {
+        pay_leaser(env, token, &leaser_renter.leasing, &collateral.token, collateral.amount)?;
+        event::collateral_forfeited(env, leaser, token, &collateral);
+    }
+
+    if relist {
+        // Set authorized to false so that user cannot transfer token unless delisted
+        for nft in nfts.iter() {
+            set_unauthorized(env, &nft, leaser);
+        }
+        leaser_renter.state = LeaseState::Listed;
+        leaser_renter.renting.rent_duration = 0;
+        leaser_renter.renting.collateral.amount = 0;
+        write_lease(env, token, &leaser_renter);
+
+        move_listing(env, LeaseState::Listed, token);
+    } else {
+        for nft in nfts.iter() {
+            set_authorized(env, &nft, leaser);
+        }
+
+        close_listing(env, token, leaser)?;
+    }
+
+    for nft in nfts.iter() {
+        remove_rented_by_user(env, renter, &nft);
+    }
+
+    event::claimed(env, leaser, token, relist);
+    Ok(bounty)
+}
+
+// Pays the keeper bounty out of the forfeited collateral, or out of the fee
+// the rental paid when there is no collateral
+fn pay_bounty(env: &Env, keeper: &Address, collateral: &mut Collateral, payment_token: &Address, rent: u128, fee: u128) -> Result<u128, LienError> {
+    let bounty_bps = read_settle_bounty(env) as u128;
+    let (bounty_token, bounty) = if collateral.amount > 0 {
+        let bounty = mul_div(collateral.amount, bounty_bps, MAX_BPS as u128);
+        collateral.amount -= bounty;
+        (collateral.token.clone(), bounty)
+    } else {
+        let accrued = read_accrued_fees(env, payment_token);
+        // Never more than the rental paid in fees, or than the contract still holds
+        let bounty = mul_div(rent, bounty_bps, MAX_BPS as u128).min(fee).min(accrued);
+        if bounty > 0 {
+            write_accrued_fees(env, payment_token, accrued - bounty);
+        }
+        (payment_token.clone(), bounty)
+    };
+
+    if bounty > 0 {
+        transfer(env, &bounty_token, keeper, to_amount(bounty)?);
+        event::bounty_paid(env, keeper, &bounty_token, bounty);
+    }
+    Ok(bounty)
+}
+
+// The role allowed to queue, execute and cancel `action`
+fn read_action_role(env: &Env, action: &AdminAction) -> Address {
+    match action {
+        AdminAction::Upgrade(_) => read_role(env, Role::Upgrader),
+        AdminAction::ChangeNftAdmin(_, _) => read_role(env, Role::CollectionManager),
+        AdminAction::ChangePaymentToken(_) | AdminAction::SetFee(_) => read_role(env, Role::FeeManager),
+        AdminAction::SetTimelockDelay(_) => read_administrator(env),
+    }
+}
+
+// Drops the lease and everything attached to it once the leaser has the nft back
+fn close_listing(env: &Env, token: &Address, leaser: &Address) -> Result<(), LienError> {
+    let nfts = lease_tokens(env, token);
+    drop_lease(env, token)?;
+
+    remove_listing(env, token);
+
+    remove_many_leased_by_user(env, leaser, &nfts);
+    Ok(())
+}
+
+// The token a lease is stored under, which for a bundle member is the bundle
+fn lease_key(env: &Env, token: &Address) -> Address {
+    read_bundle_of(env, token).unwrap_or(token.clone())
+}
+
+// Every nft moved by the lease on `token`, more than one for a bundle
+fn lease_tokens(env: &Env, token: &Address) -> Vec<Address> {
+    read_bundle(env, token).unwrap_or(vec![env, token.clone()])
+}
+
+// Drops the lease record without touching the listing indexes
+fn drop_lease(env: &Env, token: &Address) -> Result<(), LienError> {
+    clear_reservations(env, token)?;
+
+    clear_offers(env, token)?;
+
+    remove_renter_access(env, token);
+
+    remove_bundle(env, token);
+
+    remove_relist(env, token);
+
+    remove_lease(env, token);
+    Ok(())
+}
+
+// Applies new terms to one listing
+fn update_terms(env: &Env, leaser: &Address, token: &Address, price: u128, max_duration: u128) -> Result<(), LienError> {
+    if !has_lease(env, token) {
+        return Err(LienError::LeaseNotFound);
+    }
+
+    // Load lease
+    let mut leaser_renter = load_lease(env, token);
+
+    check_leaser(&leaser_renter, leaser)?;
+
+    if !is_leaseable(price, max_duration)
+        || max_duration < leaser_renter.leasing.min_duration
+    {
+        return Err(LienError::NotLeaseable);
+    }
+
+    // An active rental keeps the terms it was agreed on, see `Renting`
+    leaser_renter.leasing.price = price;
+    leaser_renter.leasing.max_duration = max_duration;
+
+    write_lease(env, token, &leaser_renter);
+
+    event::lease_updated(env, leaser, token, price, max_duration);
+    Ok(())
+}
+
+// Returns a listed nft to the leaser, leaving the listing indexes to the caller
+fn delist(env: &Env, leaser: &Address, token: &Address) -> Result<(), LienError> {
+    if !has_lease(env, token) {
+        return Err(LienError::LeaseNotFound);
+    }
+    // Load lease
+    let leaser_renter = load_lease(env, token);
+
+    if leaser_renter.state != LeaseState::Listed {
+        return Err(LienError::NotListed);
+    }
+
+    check_leaser(&leaser_renter, leaser)?;
+
+    // Set authorized to true
+    for nft in lease_tokens(env, token).iter() {
+        set_authorized(env, &nft, leaser);
+    }
+
+    drop_lease(env, token)?;
+
+    event::end_lease(env, leaser, token, 0);
+    Ok(())
+}
+
+// Rejects renters the listing is not open to
+fn check_renter_access(env: &Env, token: &Address, renter: &Address) -> Result<(), LienError> {
+    let allowed = match read_renter_access(env, token) {
+        None | Some(RenterAccess::Public) => true,
+        Some(RenterAccess::Allowlist(renters)) => renters.contains(renter),
+        Some(RenterAccess::Holder(gate, min_balance)) => balance(env, &gate, renter) as u128 >= min_balance,
+    };
+    if !allowed {
+        return Err(LienError::NotAllowed);
+    }
+    Ok(())
+}
+
+fn is_valid_access(access: &Option<RenterAccess>) -> bool {
+    match access {
+        None | Some(RenterAccess::Public) => true,
+        Some(RenterAccess::Allowlist(renters)) => !renters.is_empty(),
+        Some(RenterAccess::Holder(_, min_balance)) => *min_balance > 0,
+    }
+}
+
+// Rejects a window that overlaps a booking on the token
+fn check_available(env: &Env, token: &Address, start: u128, duration: u128) -> Result<(), LienError> {
+    let end = start.checked_add(duration).ok_or(LienError::NotRentable)?;
+    for reservation in read_reservations(env, token).iter() {
+        if start < reservation_end(&reservation)? && reservation.start < end {
+            return Err(LienError::ReservationConflict);
+        }
+    }
+    Ok(())
+}
+
+// Returns `amount` of the prepayment and the collateral held for a booking
+fn refund_reservation(env: &Env, token: &Address, reservation: &Reservation, amount: u128) -> Result<(), LienError> {
+    if amount > 0 {
+        transfer(env, &reservation.payment_token, &reservation.renter, to_amount(amount)?);
+    }
+    let collateral = &reservation.collateral;
+    if collateral.amount > 0 {
+        transfer(env, &collateral.token, &reservation.renter, to_amount(collateral.amount)?);
+    }
+
+    event::refunded(env, &reservation.renter, token, amount);
+    Ok(())
+}
+
+// Refunds every booking in full when the listing goes away
+fn clear_reservations(env: &Env, token: &Address) -> Result<(), LienError> {
+    for reservation in read_reservations(env, token).iter() {
+        refund_reservation(env, token, &reservation, reservation.amount)?;
+        event::reservation_cancelled(env, &reservation.renter, token, reservation.start, 0);
+    }
+    write_reservations(env, token, &vec![env]);
+    remove_cancellation_policy(env, token);
+    Ok(())
+}
+
+// Refunds every open offer on a listing that is closing
+fn clear_offers(env: &Env, token: &Address) -> Result<(), LienError> {
+    for renter in get_offers_by_token(env, token).iter() {
+        refund_offer(env, token, &load_offer(env, token, &renter))?;
+        event::offer_cancelled(env, &renter, token);
+    }
+    Ok(())
+}
+
+// Returns the payment and collateral held for an offer and drops it
+fn refund_offer(env: &Env, token: &Address, offer: &Offer) -> Result<(), LienError> {
+    transfer(env, &offer.payment_token, &offer.renter, to_amount(offer.amount)?);
+    if offer.collateral.amount > 0 {
+        transfer(env, &offer.collateral.token, &offer.renter, to_amount(offer.collateral.amount)?);
+    }
+    remove_offer(env, token, &offer.renter);
+
+    event::refunded(env, &offer.renter, token, offer.amount);
+    Ok(())
+}
+
+// Ties a call to the leaser recorded on the lease
+fn check_leaser(lease: &LeasingRenting, leaser: &Address) -> Result<(), LienError> {
+    if lease.leasing.leaser != *leaser {
+        return Err(LienError::NotLeaser);
+    }
+    Ok(())
+}
+
+// Only listed and rented tokens are indexed, a token without a lease has no entry
+fn check_indexed(state: &LeaseState) -> Result<(), LienError> {
+    if *state == LeaseState::Available {
+        return Err(LienError::UnindexedState);
+    }
+    Ok(())
+}
+
+// Ties a call to the renter recorded on the lease
+fn check_renter(lease: &LeasingRenting, renter: &Address) -> Result<(), LienError> {
+    if lease.renting.renter != *renter {
+        return Err(LienError::NotRenter);
+    }
+    Ok(())
+}
+
+fn check_paused(env: &Env, entry_point: EntryPoint) -> Result<(), LienError> {
+    if is_paused(env) && read_pausable(env, entry_point) {
+        return Err(LienError::Paused);
+    }
+    Ok(())
+}
+
+// A token is treated as an NFT when it has no decimals (or is an approved
+// collection, since Stellar assets always report 7), the leaser holds exactly
+// one unit and SetLien is its admin so it can freeze, claw back and mint it.
+fn check_nft(env: &Env, leaser: &Address, token: &Address) -> Result<(), LienError> {
+    if read_decimals(env, token) != Some(0) && !is_collection_approved(env, token) {
+        return Err(LienError::NotAnNft);
+    }
+
+    if read_admin(env, token) != Some(env.current_contract_address()) {
+        return Err(LienError::NotTokenAdmin);
+    }
+
+    match balance(env, token, leaser) {
+        0 => Err(LienError::InsufficientBalance),
+        NFT_BALANCE => Ok(()),
+        _ => Err(LienError::InvalidNftBalance),
+    }
+}
+
+fn is_leaseable(price: u128, duration: u128) -> bool {
+    price > 0 && duration > 0
 }
 
 fn is_rentable(
-    env: &Env,
+    _env: &Env,
     renter: &Address,
-    leaser: &Address,
+    leasing: &Leasing,
     _duration: u128,
-    max_duration: u128,
 ) -> bool {
-    if renter.eq(leaser) {
+    if renter.eq(&leasing.leaser) {
         return false;
     }
 
-    if _duration <= 0 || _duration % SECONDS_IN_DAYS != 0 {
+    if _duration == 0 || !_duration.is_multiple_of(unit_seconds(leasing.unit)) {
         return false;
     }
 
-    if _duration > max_duration {
+    if _duration < leasing.min_duration || _duration > leasing.max_duration {
         return false;
     }
     true
 }
 
-fn is_claimable(env: &Env, rented_at: u128, duration: u128, max_duration: u128) -> bool {
-    // now: 100000, rented_at: 90000, duration: 1000, max_duration: 2000
+fn is_claimable(env: &Env, rented_at: u128, duration: u128, grace_period: u128) -> Result<bool, LienError> {
+    // now: 100000, rented_at: 90000, duration: 1000, grace_period: 500
     let now = env.ledger().timestamp() as u128; // 10000
 
     log!(env, "{}, {}, {}", now, rented_at, duration);
      // rent time has not started yet (should never happen)
     if rented_at > now {
-        return false;
+        return Ok(false);
+    }
+
+    // 100000 - 90000 = 10000 <  1000 + 500 = false
+    if (now - rented_at) < duration.checked_add(grace_period).ok_or(LienError::AmountOverflow)? {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn calculate_claimable_at(lease: &LeasingRenting) -> Result<u128, LienError> {
+    rental_end(lease)?.checked_add(lease.leasing.grace_period).ok_or(LienError::AmountOverflow)
+}
+
+fn rental_end(lease: &LeasingRenting) -> Result<u128, LienError> {
+    lease.renting.rented_at.checked_add(lease.renting.rent_duration).ok_or(LienError::AmountOverflow)
+}
+
+fn reservation_end(reservation: &Reservation) -> Result<u128, LienError> {
+    reservation.start.checked_add(reservation.duration).ok_or(LienError::AmountOverflow)
+}
+
+// Price per time unit for a rental of `duration`: the rate of the longest tier
+// it reaches, or the listing price when it reaches none
+fn calculate_unit_price(leasing: &Leasing, duration: u128) -> u128 {
+    let mut price = leasing.price;
+    for tier in leasing.pricing.iter() {
+        if duration >= tier.min_duration {
+            price = tier.price;
+        }
+    }
+    price
+}
+
+fn calculate_total_price(_duration: u128, _price: u128, unit: TimeUnit) -> Result<u128, LienError> {
+    let num_units = _duration / unit_seconds(unit);
+    num_units.checked_mul(_price).ok_or(LienError::AmountOverflow)
+}
+
+fn unit_seconds(unit: TimeUnit) -> u128 {
+    match unit {
+        TimeUnit::Hour => SECONDS_IN_HOURS,
+        TimeUnit::Day => SECONDS_IN_DAYS,
+        TimeUnit::Week => SECONDS_IN_WEEKS,
+    }
+}
+
+// Token amounts are i128, anything larger cannot be transferred
+fn to_amount(amount: u128) -> Result<i128, LienError> {
+    i128::try_from(amount).map_err(|_| LienError::AmountOverflow)
+}
+
+// `amount * numerator / denominator` without overflowing the product, for
+// `numerator <= denominator`
+fn mul_div(amount: u128, numerator: u128, denominator: u128) -> u128 {
+    amount / denominator * numerator + amount % denominator * numerator / denominator
+}
+
+// Sends the protocol fee to the treasury, or keeps it in the contract for
+// `withdraw_fees` when no treasury is set. Expects `price` to already be held
+// by the contract.
+fn charge_fee(env: &Env, payment_token: &Address, price: u128) -> Result<u128, LienError> {
+    let fee = calculate_fee(env, price);
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    match read_treasury(env) {
+        Some(treasury) => transfer(env, payment_token, &treasury, to_amount(fee)?),
+        None => {
+            let accrued = read_accrued_fees(env, payment_token).checked_add(fee).ok_or(LienError::AmountOverflow)?;
+            write_accrued_fees(env, payment_token, accrued);
+        }
     }
+    Ok(fee)
+}
+
+fn calculate_fee(env: &Env, price: u128) -> u128 {
+    mul_div(price, read_fee_bps(env) as u128, MAX_BPS as u128)
+}
+
+// Share of the escrowed payment the leaser has earned so far. A time unit
+// counts as used as soon as it starts.
+fn calculate_earned(env: &Env, lease: &LeasingRenting, escrow: &Escrow) -> u128 {
+    let renting = &lease.renting;
+    let unit = unit_seconds(lease.leasing.unit);
+    let total_units = renting.rent_duration / unit;
+    if total_units == 0 {
+        return escrow.amount;
+    }
+
+    let now = env.ledger().timestamp() as u128;
+    let used_units = (now.saturating_sub(renting.rented_at) / unit + 1).min(total_units);
+    // An extension's fee is rounded on its own, which can pull the pro-rata
+    // share below what was already paid out
+    mul_div(escrow.amount, used_units, total_units).max(escrow.withdrawn)
+}
+
+// Pays out rent the leaser has earned, after the collection's royalty. A cap
+// lowered after the royalty was registered still applies.
+fn pay_rent(env: &Env, token: &Address, leasing: &Leasing, payment_token: &Address, amount: u128) -> Result<(), LienError> {
+    // Rent on a bundle is split evenly between its nfts, each paying its own
+    // collection's royalty
+    let nfts = lease_tokens(env, token);
+    let last = nfts.len() - 1;
+    let mut remaining = amount;
+    let mut royalties = 0;
+    for (i, nft) in nfts.iter().enumerate() {
+        let share = if i as u32 == last {
+            remaining
+        } else {
+            amount / nfts.len() as u128
+        };
+        remaining -= share;
+
+        if let Some(royalty) = read_royalty(env, &nft) {
+            let bps = royalty.bps.min(read_royalty_cap(env));
+            let royalty_amount = mul_div(share, bps as u128, MAX_BPS as u128);
+            if royalty_amount > 0 {
+                transfer(env, payment_token, &royalty.recipient, to_amount(royalty_amount)?);
+                event::royalty_paid(env, &royalty.recipient, &nft, payment_token, royalty_amount);
+            }
+            royalties += royalty_amount;
+        }
+    }
+
+    pay_leaser(env, token, leasing, payment_token, amount - royalties)
+}
+
+// Splits income owed to the leaser between the listing's payees. The last
+// payee takes the rounding remainder.
+fn pay_leaser(env: &Env, token: &Address, leasing: &Leasing, payment_token: &Address, amount: u128) -> Result<(), LienError> {
+    let last = leasing.payees.len() - 1;
+    let mut remaining = amount;
+    for (i, payee) in leasing.payees.iter().enumerate() {
+        let share = if i as u32 == last {
+            remaining
+        } else {
+            mul_div(amount, payee.bps as u128, MAX_BPS as u128)
+        };
+        remaining -= share;
+
+        if share > 0 {
+            transfer(env, payment_token, &payee.address, to_amount(share)?);
+            event::payee_paid(env, &payee.address, token, payment_token, share);
+        }
+    }
+    Ok(())
+}
 
-    // 100000 - 90000 = 10000 <  1000 = false
-    if (now - rented_at) < duration {
+fn is_valid_payees(payees: &Vec<Payee>) -> bool {
+    if payees.is_empty() {
+        return true;
+    }
+    if payees.len() > MAX_PAYEES {
         return false;
     }
-    true
+
+    let mut total = 0;
+    for payee in payees.iter() {
+        if payee.bps == 0 {
+            return false;
+        }
+        total += payee.bps as u128;
+    }
+    total == MAX_BPS as u128
 }
 
-fn calculate_total_price(_duration: u128, _price: u128) -> u128 {
-    let num_days = _duration / (SECONDS_IN_DAYS);
-    num_days * _price
+// Pays the leaser what is still owed and refunds the unused part to the renter
+fn release_escrow(env: &Env, token: &Address, lease: &LeasingRenting) -> Result<(), LienError> {
+    if !has_escrow(env, token) {
+        return Ok(());
+    }
+    let escrow = load_escrow(env, token);
+    let earned = calculate_earned(env, lease, &escrow);
+
+    let payout = earned - escrow.withdrawn;
+    if payout > 0 {
+        pay_rent(env, token, &lease.leasing, &escrow.payment_token, payout)?;
+        event::withdrawn(env, &lease.leasing.leaser, token, payout);
+    }
+
+    let refund = escrow.amount - earned;
+    if refund > 0 {
+        transfer(env, &escrow.payment_token, &lease.renting.renter, to_amount(refund)?);
+        event::refunded(env, &lease.renting.renter, token, refund);
+    }
+
+    remove_escrow(env, token);
+    Ok(())
 }
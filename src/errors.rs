@@ -0,0 +1,50 @@
+use soroban_sdk::contracterror;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum LienError {
+    AlreadyInitialized = 1,
+    LeaseAlreadyExists = 2,
+    NotLeaseable = 3,
+    InsufficientBalance = 4,
+    LeaseNotFound = 5,
+    NotRentable = 6,
+    NotListed = 7,
+    NotRented = 8,
+    NotClaimable = 9,
+    Paused = 10,
+    NothingToWithdraw = 11,
+    InvalidFee = 12,
+    PaymentTokenNotAccepted = 13,
+    NotAnNft = 14,
+    InvalidNftBalance = 15,
+    NotTokenAdmin = 16,
+    NotRenter = 17,
+    NotLeaser = 18,
+    OfferExists = 19,
+    OfferNotFound = 20,
+    OfferExpired = 21,
+    ReservationConflict = 22,
+    ReservationNotFound = 23,
+    ReservationNotStarted = 24,
+    ReservationExpired = 25,
+    InvalidPolicy = 26,
+    InvalidPricing = 27,
+    AmountOverflow = 28,
+    NotAllowed = 29,
+    InvalidAccess = 30,
+    InvalidPayees = 31,
+    InvalidRoyalty = 32,
+    BatchTooLarge = 33,
+    InvalidBundle = 34,
+    NoPendingAdmin = 35,
+    ActionNotFound = 36,
+    ActionNotReady = 37,
+    InvalidDelay = 38,
+    UnindexedState = 39,
+    PaymentTokenMismatch = 40,
+    InvalidGracePeriod = 41,
+    InvalidKeeper = 42,
+    TooManyOffers = 43,
+}
@@ -0,0 +1,30 @@
+use soroban_sdk::{Address, Env};
+use crate::storage_types::{DataKey, Escrow, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+pub fn write_escrow(env: &Env, token: &Address, escrow: &Escrow) {
+    let key = DataKey::Escrow(token.clone());
+    env.storage().persistent().set(&key, escrow);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn load_escrow(env: &Env, token: &Address) -> Escrow {
+    let key = DataKey::Escrow(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap()
+}
+
+pub fn has_escrow(env: &Env, token: &Address) -> bool {
+    let key = DataKey::Escrow(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        true
+    } else {
+        false
+    }
+}
+
+pub fn remove_escrow(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::Escrow(token.clone()));
+}
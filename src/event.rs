@@ -1,4 +1,41 @@
-use soroban_sdk::{Address, Env, Symbol};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+use crate::storage_types::{AdminAction, Collateral, EntryPoint, PriceTier, RenterAccess, Role, Royalty, TimeUnit};
+
+pub(crate) fn admin_proposed(e: &Env, admin: Address, new_admin: &Address) {
+    let topics = (Symbol::new(e, "admin_proposed"), admin);
+    e.events().publish(topics, new_admin.clone());
+}
+
+pub(crate) fn admin_changed(e: &Env, old_admin: &Address, new_admin: &Address) {
+    let topics = (Symbol::new(e, "admin_changed"), old_admin);
+    e.events().publish(topics, new_admin.clone());
+}
+
+pub(crate) fn role_granted(e: &Env, admin: Address, role: Role, account: &Address) {
+    let topics = (Symbol::new(e, "role_granted"), admin, role);
+    e.events().publish(topics, account.clone());
+}
+
+pub(crate) fn role_revoked(e: &Env, admin: Address, role: Role, account: Address) {
+    let topics = (Symbol::new(e, "role_revoked"), admin, role);
+    e.events().publish(topics, account);
+}
+
+pub(crate) fn action_queued(e: &Env, caller: Address, id: u32, action: AdminAction, eta: u128) {
+    let topics = (Symbol::new(e, "action_queued"), caller, id);
+    e.events().publish(topics, (action, eta));
+}
+
+pub(crate) fn action_executed(e: &Env, caller: Address, id: u32) {
+    let topics = (Symbol::new(e, "action_executed"), caller, id);
+    e.events().publish(topics, 0);
+}
+
+pub(crate) fn action_cancelled(e: &Env, caller: Address, id: u32) {
+    let topics = (Symbol::new(e, "action_cancelled"), caller, id);
+    e.events().publish(topics, 0);
+}
 
 pub(crate) fn paused(e: &Env, admin: Address) {
     let topics = (Symbol::new(e, "paused"), admin);
@@ -10,19 +47,104 @@ pub(crate) fn resumed(e: &Env, admin: Address) {
     e.events().publish(topics, false);
 }
 
+pub(crate) fn pausable_set(e: &Env, admin: Address, entry_point: EntryPoint, pausable: bool) {
+    let topics = (Symbol::new(e, "pausable_set"), admin, entry_point);
+    e.events().publish(topics, pausable);
+}
+
+pub(crate) fn payment_token_added(e: &Env, admin: Address, token: &Address) {
+    let topics = (Symbol::new(e, "payment_token_added"), admin);
+    e.events().publish(topics, token.clone());
+}
+
+pub(crate) fn payment_token_removed(e: &Env, admin: Address, token: &Address) {
+    let topics = (Symbol::new(e, "payment_token_removed"), admin);
+    e.events().publish(topics, token.clone());
+}
+
+pub(crate) fn collection_approved(e: &Env, admin: Address, token: &Address, approved: bool) {
+    let topics = (Symbol::new(e, "collection_approved"), admin, token);
+    e.events().publish(topics, approved);
+}
+
+pub(crate) fn royalty_set(e: &Env, admin: Address, token: &Address, royalty: Option<Royalty>) {
+    let topics = (Symbol::new(e, "royalty_set"), admin, token);
+    e.events().publish(topics, royalty);
+}
+
+pub(crate) fn royalty_cap_set(e: &Env, admin: Address, cap_bps: u32) {
+    let topics = (Symbol::new(e, "royalty_cap_set"), admin);
+    e.events().publish(topics, cap_bps);
+}
+
+pub(crate) fn fee_set(e: &Env, admin: Address, fee_bps: u32) {
+    let topics = (Symbol::new(e, "fee_set"), admin);
+    e.events().publish(topics, fee_bps);
+}
+
+pub(crate) fn settle_bounty_set(e: &Env, admin: Address, bounty_bps: u32) {
+    let topics = (Symbol::new(e, "settle_bounty_set"), admin);
+    e.events().publish(topics, bounty_bps);
+}
+
+pub(crate) fn time_unit_set(e: &Env, admin: Address, unit: TimeUnit) {
+    let topics = (Symbol::new(e, "time_unit_set"), admin);
+    e.events().publish(topics, unit);
+}
+
+pub(crate) fn treasury_set(e: &Env, admin: Address, treasury: Option<Address>) {
+    let topics = (Symbol::new(e, "treasury_set"), admin);
+    e.events().publish(topics, treasury);
+}
+
+pub(crate) fn fees_withdrawn(e: &Env, to: &Address, token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "fees_withdrawn"), to, token);
+    e.events().publish(topics, amount);
+}
+
+pub(crate) fn migrated(e: &Env, admin: Address, version: u32, count: u32) {
+    let topics = (Symbol::new(e, "migrated"), admin, version);
+    e.events().publish(topics, count);
+}
+
 pub(crate) fn initialized(e: &Env, admin: &Address, payment_token: &Address) {
     let topics = (Symbol::new(e, "initialized"), admin, payment_token);
     e.events().publish(topics, 0);
 }
 
-pub(crate) fn leased(e: &Env, leaser: &Address, token: &Address, price: u128, duration: u128) {
+pub(crate) fn leased(e: &Env, leaser: &Address, token: &Address, price: u128, _duration: u128) {
     let topics = (Symbol::new(e, "leased"), leaser, token);
     e.events().publish(topics, price);
 }
 
-pub(crate) fn rented(e: &Env, renter: &Address, token: &Address, duration: u128) {
+pub(crate) fn bundle_leased(e: &Env, leaser: &Address, bundle: &Address, tokens: Vec<Address>) {
+    let topics = (Symbol::new(e, "bundle_leased"), leaser, bundle);
+    e.events().publish(topics, tokens);
+}
+
+pub(crate) fn lease_updated(e: &Env, leaser: &Address, token: &Address, price: u128, max_duration: u128) {
+    let topics = (Symbol::new(e, "lease_updated"), leaser, token);
+    e.events().publish(topics, (price, max_duration));
+}
+
+pub(crate) fn renter_access_set(e: &Env, leaser: &Address, token: &Address, access: Option<RenterAccess>) {
+    let topics = (Symbol::new(e, "renter_access_set"), leaser, token);
+    e.events().publish(topics, access);
+}
+
+pub(crate) fn pricing_set(e: &Env, leaser: &Address, token: &Address, tiers: Vec<PriceTier>, min_duration: u128) {
+    let topics = (Symbol::new(e, "pricing_set"), leaser, token);
+    e.events().publish(topics, (tiers, min_duration));
+}
+
+pub(crate) fn rented(e: &Env, renter: &Address, token: &Address, duration: u128, price: u128, fee: u128) {
     let topics = (Symbol::new(e, "rented"), renter, token);
-    e.events().publish(topics, duration);
+    e.events().publish(topics, (duration, price, fee));
+}
+
+pub(crate) fn extended(e: &Env, renter: &Address, token: &Address, extra_duration: u128, price: u128, fee: u128) {
+    let topics = (Symbol::new(e, "extended"), renter, token);
+    e.events().publish(topics, (extra_duration, price, fee));
 }
 
 pub(crate) fn returned(e: &Env, renter: &Address, token: &Address, amount: i128) {
@@ -35,7 +157,102 @@ pub(crate) fn end_lease(e: &Env, leaser: &Address, token: &Address, amount: i128
     e.events().publish(topics, amount);
 }
 
+pub(crate) fn settled(e: &Env, keeper: &Address, token: &Address, bounty: u128) {
+    let topics = (Symbol::new(e, "settled"), keeper, token);
+    e.events().publish(topics, bounty);
+}
+
+pub(crate) fn bounty_paid(e: &Env, keeper: &Address, payment_token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "bounty_paid"), keeper, payment_token);
+    e.events().publish(topics, amount);
+}
+
+pub(crate) fn relist_set(e: &Env, leaser: &Address, token: &Address, relist: bool) {
+    let topics = (Symbol::new(e, "relist_set"), leaser, token);
+    e.events().publish(topics, relist);
+}
+
 pub(crate) fn claimed(e: &Env, leaser: &Address, token: &Address, relist: bool) {
     let topics = (Symbol::new(e, "claimed"), leaser, token);
     e.events().publish(topics, relist);
-}
\ No newline at end of file
+}
+
+pub(crate) fn escrowed(e: &Env, renter: &Address, token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "escrowed"), renter, token);
+    e.events().publish(topics, amount);
+}
+
+pub(crate) fn royalty_paid(e: &Env, recipient: &Address, token: &Address, payment_token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "royalty_paid"), recipient, token);
+    e.events().publish(topics, (payment_token.clone(), amount));
+}
+
+pub(crate) fn payee_paid(e: &Env, payee: &Address, token: &Address, payment_token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "payee_paid"), payee, token);
+    e.events().publish(topics, (payment_token.clone(), amount));
+}
+
+pub(crate) fn withdrawn(e: &Env, leaser: &Address, token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "withdrawn"), leaser, token);
+    e.events().publish(topics, amount);
+}
+
+pub(crate) fn refunded(e: &Env, renter: &Address, token: &Address, amount: u128) {
+    let topics = (Symbol::new(e, "refunded"), renter, token);
+    e.events().publish(topics, amount);
+}
+
+pub(crate) fn collateral_locked(e: &Env, renter: &Address, token: &Address, collateral: &Collateral) {
+    let topics = (Symbol::new(e, "collateral_locked"), renter, token);
+    e.events().publish(topics, collateral.clone());
+}
+
+pub(crate) fn collateral_returned(e: &Env, renter: &Address, token: &Address, collateral: &Collateral) {
+    let topics = (Symbol::new(e, "collateral_returned"), renter, token);
+    e.events().publish(topics, collateral.clone());
+}
+
+pub(crate) fn collateral_forfeited(e: &Env, leaser: &Address, token: &Address, collateral: &Collateral) {
+    let topics = (Symbol::new(e, "collateral_forfeited"), leaser, token);
+    e.events().publish(topics, collateral.clone());
+}
+
+pub(crate) fn offer_made(e: &Env, renter: &Address, token: &Address, price: u128, duration: u128, expiry: u128) {
+    let topics = (Symbol::new(e, "offer_made"), renter, token);
+    e.events().publish(topics, (price, duration, expiry));
+}
+
+pub(crate) fn offer_accepted(e: &Env, leaser: &Address, token: &Address, renter: &Address) {
+    let topics = (Symbol::new(e, "offer_accepted"), leaser, token);
+    e.events().publish(topics, renter.clone());
+}
+
+pub(crate) fn offer_rejected(e: &Env, leaser: &Address, token: &Address, renter: &Address) {
+    let topics = (Symbol::new(e, "offer_rejected"), leaser, token);
+    e.events().publish(topics, renter.clone());
+}
+
+pub(crate) fn offer_cancelled(e: &Env, renter: &Address, token: &Address) {
+    let topics = (Symbol::new(e, "offer_cancelled"), renter, token);
+    e.events().publish(topics, ());
+}
+
+pub(crate) fn reserved(e: &Env, renter: &Address, token: &Address, start: u128, duration: u128, amount: u128) {
+    let topics = (Symbol::new(e, "reserved"), renter, token);
+    e.events().publish(topics, (start, duration, amount));
+}
+
+pub(crate) fn reservation_activated(e: &Env, renter: &Address, token: &Address, start: u128) {
+    let topics = (Symbol::new(e, "reservation_activated"), renter, token);
+    e.events().publish(topics, start);
+}
+
+pub(crate) fn reservation_cancelled(e: &Env, renter: &Address, token: &Address, start: u128, penalty: u128) {
+    let topics = (Symbol::new(e, "reservation_cancelled"), renter, token);
+    e.events().publish(topics, (start, penalty));
+}
+
+pub(crate) fn cancellation_policy_set(e: &Env, leaser: &Address, token: &Address, notice: u128, penalty_bps: u32) {
+    let topics = (Symbol::new(e, "cancellation_policy_set"), leaser, token);
+    e.events().publish(topics, (notice, penalty_bps));
+}
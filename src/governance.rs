@@ -0,0 +1,47 @@
+use soroban_sdk::Env;
+use crate::storage_types::{
+    DataKey, QueuedAction, INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD,
+    LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT,
+};
+
+// Seconds a sensitive admin action waits between being queued and executed
+const DEFAULT_TIMELOCK_DELAY: u128 = 2 * 86400;
+
+pub fn read_timelock_delay(e: &Env) -> u128 {
+    let key = DataKey::TimelockDelay;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().get(&key).unwrap_or(DEFAULT_TIMELOCK_DELAY)
+}
+
+pub fn write_timelock_delay(e: &Env, delay: u128) {
+    let key = DataKey::TimelockDelay;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    e.storage().instance().set(&key, &delay);
+}
+
+// Ids are never reused, so an executed or cancelled action cannot be replayed
+pub fn next_action_id(e: &Env) -> u32 {
+    let key = DataKey::NextActionId;
+    e.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT * 2);
+    let id: u32 = e.storage().instance().get(&key).unwrap_or(0);
+    e.storage().instance().set(&key, &(id + 1));
+    id
+}
+
+pub fn write_queued_action(e: &Env, id: u32, queued: &QueuedAction) {
+    let key = DataKey::QueuedAction(id);
+    e.storage().persistent().set(&key, queued);
+    e.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn read_queued_action(e: &Env, id: u32) -> Option<QueuedAction> {
+    let key = DataKey::QueuedAction(id);
+    if e.storage().persistent().has(&key) {
+        e.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    e.storage().persistent().get(&key)
+}
+
+pub fn remove_queued_action(e: &Env, id: u32) {
+    e.storage().persistent().remove(&DataKey::QueuedAction(id));
+}
@@ -1,6 +1,6 @@
 
 use soroban_sdk::{Address, Env, Vec, vec};
-use crate::storage_types::{LeasingRenting, DataKey, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+use crate::storage_types::{LeaseState, LeasingRenting, DataKey, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
 
 pub fn write_lease(env: &Env, token: &Address, lease: &LeasingRenting) {
     let key = DataKey::Lease(token.clone());
@@ -34,38 +34,132 @@ pub fn remove_lease(env: &Env, token: &Address) {
     env.storage().persistent().remove(&DataKey::Lease(token.clone()));
 }
 
-pub fn add_all_listed(env: &Env, token: &Address) -> bool {
-    let mut all_leases = get_all_listed(env);
-    if all_leases.contains(token) {
+// Whether `settle` relists the token or hands it back to the leaser
+pub fn read_relist(env: &Env, token: &Address) -> bool {
+    let key = DataKey::Relist(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap_or(false)
+}
+
+pub fn write_relist(env: &Env, token: &Address, relist: bool) {
+    let key = DataKey::Relist(token.clone());
+    env.storage().persistent().set(&key, &relist);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn remove_relist(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::Relist(token.clone()));
+}
+
+pub fn add_listing(env: &Env, state: LeaseState, token: &Address) -> bool {
+    let count = get_listing_count(env, state.clone());
+    if !push_listing(env, &state, token, count) {
         return false
     }
-    
-    all_leases.push_back(token.clone());
-    env.storage().persistent().set(&DataKey::AllListed, &all_leases);
-    return true
+    write_listing_count(env, state, count + 1);
+    true
+}
+
+// Adds every token under `state`, writing the count once
+pub fn add_listings(env: &Env, state: LeaseState, tokens: &Vec<Address>) {
+    let mut count = get_listing_count(env, state.clone());
+    for token in tokens.iter() {
+        if push_listing(env, &state, &token, count) {
+            count += 1;
+        }
+    }
+    write_listing_count(env, state, count);
 }
 
-pub fn remove_all_listed(env: &Env, token: &Address) -> bool {
-    let mut all_leases = get_all_listed(env);
-    if !all_leases.contains(token) {
+fn push_listing(env: &Env, state: &LeaseState, token: &Address, count: u32) -> bool {
+    let index_key = DataKey::ListingIndex(token.clone());
+    if env.storage().persistent().has(&index_key) {
         return false
     }
-    
-    if let Some(o) = all_leases.first_index_of(token.clone()) {
-        all_leases.remove_unchecked(o);
-        env.storage().persistent().set(&DataKey::AllListed, &all_leases);
-        return true
+
+    let key = DataKey::Listing(state.clone(), count);
+    env.storage().persistent().set(&key, token);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    env.storage().persistent().set(&index_key, &(state.clone(), count));
+    env.storage().persistent().extend_ttl(&index_key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    true
+}
+
+// Swaps the last listing of the same state into the removed slot so the cost
+// does not depend on the number of listings.
+pub fn remove_listing(env: &Env, token: &Address) -> bool {
+    let index_key = DataKey::ListingIndex(token.clone());
+    let (state, position): (LeaseState, u32) = match env.storage().persistent().get(&index_key) {
+        Some(index) => index,
+        None => return false,
+    };
+
+    let last = get_listing_count(env, state.clone()) - 1;
+    swap_remove_listing(env, &state, token, position, last);
+    write_listing_count(env, state, last);
+    true
+}
+
+// Removes every token listed under `state`, writing the count once
+pub fn remove_listings(env: &Env, state: LeaseState, tokens: &Vec<Address>) {
+    let mut count = get_listing_count(env, state.clone());
+    for token in tokens.iter() {
+        let index: Option<(LeaseState, u32)> = env.storage().persistent().get(&DataKey::ListingIndex(token.clone()));
+        if let Some((listed, position)) = index {
+            if listed == state {
+                swap_remove_listing(env, &state, &token, position, count - 1);
+                count -= 1;
+            }
+        }
     }
-    
-    return false
+    write_listing_count(env, state, count);
 }
 
-pub fn get_all_listed(env: &Env) -> Vec<Address> {
-    let key = DataKey::AllListed;
+fn swap_remove_listing(env: &Env, state: &LeaseState, token: &Address, position: u32, last: u32) {
+    if position != last {
+        let moved: Address = env.storage().persistent().get(&DataKey::Listing(state.clone(), last)).unwrap();
+        let key = DataKey::Listing(state.clone(), position);
+        env.storage().persistent().set(&key, &moved);
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        let moved_key = DataKey::ListingIndex(moved);
+        env.storage().persistent().set(&moved_key, &(state.clone(), position));
+        env.storage().persistent().extend_ttl(&moved_key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+
+    env.storage().persistent().remove(&DataKey::Listing(state.clone(), last));
+    env.storage().persistent().remove(&DataKey::ListingIndex(token.clone()));
+}
+
+pub fn move_listing(env: &Env, state: LeaseState, token: &Address) -> bool {
+    remove_listing(env, token);
+    add_listing(env, state, token)
+}
+
+pub fn get_listing_count(env: &Env, state: LeaseState) -> u32 {
+    let key = DataKey::ListingCount(state);
     if env.storage().persistent().has(&key) {
         env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
     }
-    env.storage().persistent().get(&key).unwrap_or(vec![env])
+    env.storage().persistent().get(&key).unwrap_or(0)
+}
+
+fn write_listing_count(env: &Env, state: LeaseState, count: u32) {
+    let key = DataKey::ListingCount(state);
+    env.storage().persistent().set(&key, &count);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn get_listings(env: &Env, state: LeaseState, offset: u32, limit: u32) -> Vec<Address> {
+    let mut listings = vec![env];
+    let end = get_listing_count(env, state.clone()).min(offset.saturating_add(limit));
+    for position in offset..end {
+        let key = DataKey::Listing(state.clone(), position);
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        listings.push_back(env.storage().persistent().get(&key).unwrap());
+    }
+    listings
 }
 
 pub fn add_leased_by_user(env: &Env, leaser: &Address, token: &Address) -> bool {
@@ -76,22 +170,27 @@ pub fn add_leased_by_user(env: &Env, leaser: &Address, token: &Address) -> bool
     
     leased_by_user.push_back(token.clone());
     env.storage().persistent().set(&DataKey::LeasedByUser(leaser.clone()), &leased_by_user);
-    return true
+    true
 }
 
-pub fn remove_leased_by_user(env: &Env, leaser: &Address, token: &Address) -> bool {
+pub fn add_many_leased_by_user(env: &Env, leaser: &Address, tokens: &Vec<Address>) {
     let mut leased_by_user = get_leased_by_user(env, leaser);
-    if !leased_by_user.contains(token) {
-        return false
+    for token in tokens.iter() {
+        if !leased_by_user.contains(&token) {
+            leased_by_user.push_back(token);
+        }
     }
-    
-    if let Some(o) = leased_by_user.first_index_of(token.clone()) {
-        leased_by_user.remove_unchecked(o);
-        env.storage().persistent().set(&DataKey::LeasedByUser(leaser.clone()), &leased_by_user);
-        return true
+    env.storage().persistent().set(&DataKey::LeasedByUser(leaser.clone()), &leased_by_user);
+}
+
+pub fn remove_many_leased_by_user(env: &Env, leaser: &Address, tokens: &Vec<Address>) {
+    let mut leased_by_user = get_leased_by_user(env, leaser);
+    for token in tokens.iter() {
+        if let Some(o) = leased_by_user.first_index_of(token) {
+            leased_by_user.remove_unchecked(o);
+        }
     }
-    
-    return false
+    env.storage().persistent().set(&DataKey::LeasedByUser(leaser.clone()), &leased_by_user);
 }
 
 pub fn get_leased_by_user(env: &Env, leaser: &Address) -> Vec<Address> {
@@ -109,7 +208,7 @@ pub fn add_rented_by_user(env: &Env, renter: &Address, token: &Address) -> bool
     
     rented_by_user.push_back(token.clone());
     env.storage().persistent().set(&DataKey::RentedByUser(renter.clone()), &rented_by_user);
-    return true
+    true
 }
 
 pub fn remove_rented_by_user(env: &Env, renter: &Address, token: &Address) -> bool {
@@ -124,7 +223,7 @@ pub fn remove_rented_by_user(env: &Env, renter: &Address, token: &Address) -> bo
         return true
     }
     
-    return false
+    false
 }
 
 pub fn get_rented_by_user(env: &Env, renter: &Address) -> Vec<Address> {
@@ -1,16 +1,24 @@
 #![no_std]
 
-mod utils;
 mod token;
+mod access;
 mod admin;
+mod bundle;
+mod collection;
 mod contract;
 mod errors;
+mod escrow;
 mod event;
+mod governance;
 mod metadata;
+mod migration;
 mod storage_types;
 mod lease;
+mod offer;
+mod reservation;
 mod token_utils;
 
 #[cfg(test)]
 mod test;
-pub use crate::contract::SetLien;
\ No newline at end of file
+pub use crate::contract::{LienTrait, SetLien, SetLienClient};
+pub use crate::errors::LienError;
\ No newline at end of file
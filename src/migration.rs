@@ -0,0 +1,105 @@
+use soroban_sdk::{contracttype, symbol_short, vec, Address, Env, Map, Symbol, TryFromVal, Val, Vec};
+use crate::storage_types::{
+    Collateral, DataKey, LeaseState, Leasing, LeasingRenting, Payee, Renting, TimeUnit,
+    LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT,
+};
+
+// Layout of `LeasingRenting` before storage was versioned (version 1)
+#[derive(Clone)]
+#[contracttype]
+pub struct LeasingV1 {
+    pub leaser: Address,
+    pub max_duration: u128,
+    pub price: u128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct RentingV1 {
+    pub renter: Address,
+    pub rent_duration: u128,
+    pub rented_at: u128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct LeasingRentingV1 {
+    pub leasing: LeasingV1,
+    pub renting: RentingV1,
+    pub state: LeaseState,
+}
+
+// None when there is no lease or it is already in the current layout
+pub fn read_lease_v1(env: &Env, token: &Address) -> Option<LeasingRentingV1> {
+    let key = DataKey::Lease(token.clone());
+    let raw: Map<Symbol, Val> = env.storage().persistent().get(&key)?;
+    // Unpacking a map with a different number of fields traps, so tell the
+    // layouts apart by the size of `leasing` first
+    let leasing = Map::<Symbol, Val>::try_from_val(env, &raw.get(symbol_short!("leasing"))?).ok()?;
+    if leasing.len() != 3 {
+        return None;
+    }
+    LeasingRentingV1::try_from_val(env, &raw.to_val()).ok()
+}
+
+// Version 1 charged every lease in the contract payment token, per day, and
+// paid the leaser upfront, so there is no escrow or collateral to carry over
+pub fn upgrade_lease(env: &Env, lease: LeasingRentingV1, payment_token: &Address) -> LeasingRenting {
+    let no_collateral = Collateral {
+        token: payment_token.clone(),
+        amount: 0,
+    };
+    let leasing = Leasing {
+        leaser: lease.leasing.leaser.clone(),
+        payment_token: payment_token.clone(),
+        max_duration: lease.leasing.max_duration,
+        price: lease.leasing.price,
+        grace_period: 0,
+        collateral: no_collateral.clone(),
+        pricing: vec![env],
+        min_duration: 0,
+        unit: TimeUnit::Day,
+        payees: vec![env, Payee { address: lease.leasing.leaser, bps: 10_000 }],
+    };
+    let renting = Renting {
+        renter: lease.renting.renter,
+        rent_duration: lease.renting.rent_duration,
+        rented_at: lease.renting.rented_at,
+        price: leasing.price,
+        max_duration: leasing.max_duration,
+        collateral: no_collateral,
+    };
+
+    LeasingRenting {
+        leasing,
+        renting,
+        state: lease.state,
+    }
+}
+
+// Version 1 kept every lease in one unbounded `AllListed` list, and left it
+// behind empty once its leases ended. Removes an empty list, true once the
+// list is gone and no lease is left to migrate.
+pub fn remove_all_listed_if_empty(env: &Env) -> bool {
+    let key = DataKey::AllListed;
+    let all_listed: Vec<Address> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+    if all_listed.is_empty() {
+        env.storage().persistent().remove(&key);
+    }
+    all_listed.is_empty()
+}
+
+// Version 1 kept every lease in one unbounded `AllListed` list
+pub fn remove_all_listed(env: &Env, token: &Address) {
+    let key = DataKey::AllListed;
+    let mut all_listed: Vec<Address> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+    if let Some(o) = all_listed.first_index_of(token.clone()) {
+        all_listed.remove_unchecked(o);
+        if all_listed.is_empty() {
+            env.storage().persistent().remove(&key);
+        } else {
+            env.storage().persistent().set(&key, &all_listed);
+            env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        }
+    }
+}
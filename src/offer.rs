@@ -0,0 +1,72 @@
+use soroban_sdk::{vec, Address, Env, Vec};
+use crate::storage_types::{DataKey, Offer, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+pub fn write_offer(env: &Env, token: &Address, offer: &Offer) {
+    let key = DataKey::Offer(token.clone(), offer.renter.clone());
+    env.storage().persistent().set(&key, offer);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+
+    let mut by_token = get_offers_by_token(env, token);
+    if !by_token.contains(&offer.renter) {
+        by_token.push_back(offer.renter.clone());
+        env.storage().persistent().set(&DataKey::OffersByToken(token.clone()), &by_token);
+    }
+
+    let mut by_renter = get_offers_by_renter(env, &offer.renter);
+    if !by_renter.contains(token) {
+        by_renter.push_back(token.clone());
+        env.storage().persistent().set(&DataKey::OffersByRenter(offer.renter.clone()), &by_renter);
+    }
+}
+
+pub fn load_offer(env: &Env, token: &Address, renter: &Address) -> Offer {
+    let key = DataKey::Offer(token.clone(), renter.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap()
+}
+
+pub fn has_offer(env: &Env, token: &Address, renter: &Address) -> bool {
+    let key = DataKey::Offer(token.clone(), renter.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+        true
+    } else {
+        false
+    }
+}
+
+pub fn remove_offer(env: &Env, token: &Address, renter: &Address) {
+    env.storage().persistent().remove(&DataKey::Offer(token.clone(), renter.clone()));
+
+    let mut by_token = get_offers_by_token(env, token);
+    if let Some(i) = by_token.first_index_of(renter.clone()) {
+        by_token.remove_unchecked(i);
+        env.storage().persistent().set(&DataKey::OffersByToken(token.clone()), &by_token);
+    }
+
+    let mut by_renter = get_offers_by_renter(env, renter);
+    if let Some(i) = by_renter.first_index_of(token.clone()) {
+        by_renter.remove_unchecked(i);
+        env.storage().persistent().set(&DataKey::OffersByRenter(renter.clone()), &by_renter);
+    }
+}
+
+// Renters with an open offer on `token`
+pub fn get_offers_by_token(env: &Env, token: &Address) -> Vec<Address> {
+    let key = DataKey::OffersByToken(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap_or(vec![env])
+}
+
+// Tokens `renter` has an open offer on
+pub fn get_offers_by_renter(env: &Env, renter: &Address) -> Vec<Address> {
+    let key = DataKey::OffersByRenter(renter.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap_or(vec![env])
+}
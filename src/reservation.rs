@@ -0,0 +1,53 @@
+use soroban_sdk::{vec, Address, Env, Vec};
+use crate::storage_types::{CancellationPolicy, DataKey, Reservation, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT};
+
+// Bookings on `token`, ordered by start
+pub fn read_reservations(env: &Env, token: &Address) -> Vec<Reservation> {
+    let key = DataKey::Reservations(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap_or(vec![env])
+}
+
+pub fn write_reservations(env: &Env, token: &Address, reservations: &Vec<Reservation>) {
+    let key = DataKey::Reservations(token.clone());
+    if reservations.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, reservations);
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+}
+
+pub fn add_reservation(env: &Env, token: &Address, reservation: &Reservation) {
+    let mut reservations = read_reservations(env, token);
+    let mut i = 0;
+    while i < reservations.len() && reservations.get_unchecked(i).start < reservation.start {
+        i += 1;
+    }
+    reservations.insert(i, reservation.clone());
+    write_reservations(env, token, &reservations);
+}
+
+pub fn has_reservations(env: &Env, token: &Address) -> bool {
+    env.storage().persistent().has(&DataKey::Reservations(token.clone()))
+}
+
+pub fn read_cancellation_policy(env: &Env, token: &Address) -> CancellationPolicy {
+    let key = DataKey::CancellationPolicy(token.clone());
+    if env.storage().persistent().has(&key) {
+        env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+    }
+    env.storage().persistent().get(&key).unwrap_or(CancellationPolicy { notice: 0, penalty_bps: 0 })
+}
+
+pub fn write_cancellation_policy(env: &Env, token: &Address, policy: &CancellationPolicy) {
+    let key = DataKey::CancellationPolicy(token.clone());
+    env.storage().persistent().set(&key, policy);
+    env.storage().persistent().extend_ttl(&key, LEASEE_LIFETIME_THRESHOLD, LEASE_BUMP_AMOUNT);
+}
+
+pub fn remove_cancellation_policy(env: &Env, token: &Address) {
+    env.storage().persistent().remove(&DataKey::CancellationPolicy(token.clone()));
+}
@@ -1,4 +1,4 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, BytesN, Vec};
 
 pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
 pub(crate) const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS; // 2 days
@@ -15,12 +15,151 @@ pub enum LeaseState {
     Rented,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[contracttype]
+pub enum EntryPoint {
+    Lease,
+    Rent,
+    Extend,
+    UpdateLease,
+    EndLease,
+    EndRent,
+    Claim,
+    Withdraw,
+    Offer,
+    Reserve,
+}
+
+// Sensitive admin changes that only take effect after the timelock delay
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub enum AdminAction {
+    Upgrade(BytesN<32>),
+    // NFT contract, its new admin
+    ChangeNftAdmin(Address, Address),
+    ChangePaymentToken(Address),
+    SetFee(u32),
+    SetTimelockDelay(u128),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct QueuedAction {
+    pub action: AdminAction,
+    // Earliest timestamp the action can be executed at
+    pub eta: u128,
+}
+
+// Admin duties that can be handed to an account other than the admin
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[contracttype]
+pub enum Role {
+    Pauser,
+    FeeManager,
+    Upgrader,
+    CollectionManager,
+}
+
+// Unit rental durations are counted and priced in
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[contracttype]
+pub enum TimeUnit {
+    Hour,
+    Day,
+    Week,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct Collateral {
+    pub token: Address,
+    pub amount: u128,
+}
+
+// One token in a batch listing or price update
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct LeaseEntry {
+    pub token: Address,
+    pub price: u128,
+    pub duration: u128,
+}
+
+// Restricts who can rent a listing
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub enum RenterAccess {
+    Allowlist(Vec<Address>),
+    // Holders of at least `u128` of the token contract
+    Holder(Address, u128),
+    // Anyone, the same as no restriction
+    Public,
+}
+
+// Optional terms of a listing, on top of its price and maximum duration
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct LeaseTerms {
+    pub grace_period: u128,
+    // A zero amount means no collateral is required
+    pub collateral: Collateral,
+    pub access: RenterAccess,
+    // Empty pays everything to the leaser
+    pub payees: Vec<Payee>,
+}
+
+// Creator's cut of the rent earned on a collection
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct Royalty {
+    pub recipient: Address,
+    pub bps: u32,
+}
+
+// Share of the leaser's income paid to `address`
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct Payee {
+    pub address: Address,
+    pub bps: u32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct PriceTier {
+    // Shortest rental the tier applies to
+    pub min_duration: u128,
+    pub price: u128,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct RentQuote {
+    pub units: u128,
+    pub price_per_unit: u128,
+    // Total paid by the renter, the fee is taken out of it
+    pub price: u128,
+    pub fee: u128,
+    pub collateral: Collateral,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Leasing {
     pub leaser: Address,
+    pub payment_token: Address,
     pub max_duration: u128,
     pub price: u128,
+    pub grace_period: u128,
+    // A zero amount means no collateral is required
+    pub collateral: Collateral,
+    // Discounted unit prices for longer rentals, `price` applies below the first tier
+    pub pricing: Vec<PriceTier>,
+    pub min_duration: u128,
+    // `price` is per unit, fixed when the token is listed
+    pub unit: TimeUnit,
+    // Recipients of rent and forfeited collateral, adding up to 100%
+    pub payees: Vec<Payee>,
 }
 
 #[derive(Clone)]
@@ -29,6 +168,57 @@ pub struct Renting {
     pub renter: Address,
     pub rent_duration: u128,
     pub rented_at: u128,
+    // Listing terms the rental was agreed on
+    pub price: u128,
+    pub max_duration: u128,
+    // Collateral locked by the renter
+    pub collateral: Collateral,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Escrow {
+    pub payment_token: Address,
+    pub amount: u128,
+    pub withdrawn: u128,
+    // Protocol fee charged on the rental, the most a keeper can be paid from fees
+    pub fee: u128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Offer {
+    pub renter: Address,
+    pub payment_token: Address,
+    // Offered price per time unit
+    pub price: u128,
+    pub duration: u128,
+    pub expiry: u128,
+    // Payment and collateral held by the contract until the offer is settled
+    pub amount: u128,
+    pub collateral: Collateral,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Reservation {
+    pub renter: Address,
+    pub payment_token: Address,
+    pub start: u128,
+    pub duration: u128,
+    // Price per time unit at booking time
+    pub price: u128,
+    // Payment and collateral held by the contract until the booking starts
+    pub amount: u128,
+    pub collateral: Collateral,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[contracttype]
+pub struct CancellationPolicy {
+    // Cancelling less than `notice` seconds before the start costs a penalty
+    pub notice: u128,
+    pub penalty_bps: u32,
 }
 
 #[derive(Clone)]
@@ -43,11 +233,41 @@ pub struct LeasingRenting {
 #[contracttype]
 pub enum DataKey {
     PaymentToken,
+    PaymentTokens,
     Admin,
+    PendingAdmin,
+    Version,
+    TimelockDelay,
+    NextActionId,
+    QueuedAction(u32),
+    Role(Role),
     Paused,
+    TimeUnit,
+    FeeBps,
+    RoyaltyCap,
+    SettleBounty,
+    Treasury,
+    AccruedFees(Address),
+    Pausable(EntryPoint),
     Count,
     Lease(Address),
+    Escrow(Address),
+    // Unbounded list of every leased token, superseded by the `Listing` index
     AllListed,
+    Listing(LeaseState, u32),
+    ListingCount(LeaseState),
+    ListingIndex(Address),
+    ApprovedCollection(Address),
+    Royalty(Address),
     LeasedByUser(Address),
     RentedByUser(Address),
+    Offer(Address, Address),
+    OffersByToken(Address),
+    OffersByRenter(Address),
+    Reservations(Address),
+    CancellationPolicy(Address),
+    RenterAccess(Address),
+    Bundle(Address),
+    BundleOf(Address),
+    Relist(Address),
 }
\ No newline at end of file
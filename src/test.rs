@@ -1,8 +1,12 @@
 #![cfg(test)]
+#![allow(clippy::bool_assert_comparison, clippy::identity_op, clippy::unnecessary_cast)]
 extern crate std;
 
-use crate::{contract::SetLien, contract::SetLienClient, storage_types::LeaseState, token};
-use soroban_sdk::{testutils::Address as _, Address, Env, IntoVal, token::TokenClient};
+use crate::{contract::SetLien, contract::SetLienClient, errors::LienError, storage_types::{AdminAction, DataKey, Collateral, EntryPoint, LeaseEntry, LeaseState, LeaseTerms, Payee, PriceTier, RenterAccess, Role, TimeUnit}, token};
+use soroban_sdk::{testutils::{Address as _, IssuerFlags, Ledger}, token::StellarAssetClient, vec, Address, Env, IntoVal};
+use std::println;
+
+const EXPIRATION_LEDGER: u32 = 1000;
 
 fn create_setlien<'a>(e: &Env, admin: &Address, payment_token: &Address) -> SetLienClient<'a> {
     let token = SetLienClient::new(e, &e.register_contract(None, SetLien {}));
@@ -10,12 +14,13 @@ fn create_setlien<'a>(e: &Env, admin: &Address, payment_token: &Address) -> SetL
     token
 }
 
-fn create_token<'a>(e: &Env, admin: &Address) -> TokenClient<'a> {
-    let token =  TokenClient::new(e, &e.register_contract_wasm(None, token::WASM));
-    // token::Client::new(e, &e.register_stellar_asset_contract(admin.clone()));
-    
-    token.initialize(admin, &7, &"name".into_val(e), &"symbol".into_val(e));
-    token
+fn create_token<'a>(e: &Env, admin: &Address) -> token::Client<'a> {
+    let sac = e.register_stellar_asset_contract_v2(admin.clone());
+    // SetLien freezes balances and claws back overdue tokens
+    sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+    sac.issuer().set_flag(IssuerFlags::ClawbackEnabledFlag);
+
+    token::Client::new(e, &sac.address())
 }
 
 #[test]
@@ -43,11 +48,16 @@ fn test() {
 
     let set_lien: SetLienClient<'_> = create_setlien(&e, &admin, &payment);
 
+    token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &10, &EXPIRATION_LEDGER);
+
     let price = 10;
     let max_duration = 30 * 24 * 60 * 60;
     let duration = 1 * 24 * 60 * 60;
 
-    set_lien.lease(&leaser, &token, &price, &max_duration);
+    set_lien.lease(&leaser, &token, &payment, &price, &max_duration, &no_terms(&e, &payment));
     // assert_eq!(
     //     e.auths(),
     //     [(
@@ -66,7 +76,7 @@ fn test() {
     assert_eq!(price, lease.leasing.price);
     // Verify balance
     assert_eq!(1, token_client.balance(&leaser));
-    assert_eq!(false, token_client.authorized(&leaser));
+    assert_eq!(false, StellarAssetClient::new(&e, &token).authorized(&leaser));
 
     set_lien.rent(&renter, &token, &duration);
     // assert_eq!(
@@ -95,8 +105,11 @@ fn test() {
     // assert_eq!(false, token_client.authorized(&renter));
 
     assert_eq!(0 as i128, payment_client.balance(&renter));
-    assert_eq!(price as i128, payment_client.balance(&leaser));
+    // payment is held in escrow until it is earned
+    assert_eq!(0 as i128, payment_client.balance(&leaser));
+    assert_eq!(price as i128, payment_client.balance(&set_lien.address));
 
+    token_client.approve(&renter, &set_lien.address, &1, &EXPIRATION_LEDGER);
     set_lien.end_rent(&renter, &token);
     let has_lease = set_lien.has_lease(&token);
     assert_eq!(false, has_lease);
@@ -138,11 +151,15 @@ fn test_lease() {
 
     let set_lien: SetLienClient<'_> = create_setlien(&e, &admin, &payment);
 
+    token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &10, &EXPIRATION_LEDGER);
+
     let price = 10;
     let max_duration = 30 * 24 * 60 * 60;
-    let duration = 1 * 24 * 60 * 60;
 
-    set_lien.lease(&leaser, &token, &price, &max_duration);
+    set_lien.lease(&leaser, &token, &payment, &price, &max_duration, &no_terms(&e, &payment));
     // assert_eq!(
     //     e.auths(),
     //     [(
@@ -190,11 +207,15 @@ fn test_end_lease() {
 
     let set_lien: SetLienClient<'_> = create_setlien(&e, &admin, &payment);
 
+    token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &10, &EXPIRATION_LEDGER);
+
     let price = 10;
     let max_duration = 30 * 24 * 60 * 60;
-    let duration = 1 * 24 * 60 * 60;
 
-    set_lien.lease(&leaser, &token, &price, &max_duration);
+    set_lien.lease(&leaser, &token, &payment, &price, &max_duration, &no_terms(&e, &payment));
     // assert_eq!(
     //     e.auths(),
     //     [(
@@ -256,11 +277,16 @@ fn test_rent() {
 
     let set_lien: SetLienClient<'_> = create_setlien(&e, &admin, &payment);
 
+    token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &10, &EXPIRATION_LEDGER);
+
     let price = 10;
     let max_duration = 30 * 24 * 60 * 60;
     let duration = 1 * 24 * 60 * 60;
 
-    set_lien.lease(&leaser, &token, &price, &max_duration);
+    set_lien.lease(&leaser, &token, &payment, &price, &max_duration, &no_terms(&e, &payment));
     // assert_eq!(
     //     e.auths(),
     //     [(
@@ -314,7 +340,9 @@ fn test_rent() {
     // assert_eq!(false, token_client.authorized(&renter));
 
     assert_eq!(0 as i128, payment_client.balance(&renter));
-    assert_eq!(price as i128, payment_client.balance(&leaser));
+    // payment is held in escrow until it is earned
+    assert_eq!(0 as i128, payment_client.balance(&leaser));
+    assert_eq!(price as i128, payment_client.balance(&set_lien.address));
 
 }
 
@@ -345,13 +373,16 @@ fn test_claim() {
     println!("{}, {:?}", payment_client.balance(&renter), payment);
     
     let set_lien: SetLienClient<'_> = create_setlien(&e, &admin, &payment);
-    
+
     token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &10, &EXPIRATION_LEDGER);
     let price = 10;
     let max_duration = 30 * 24 * 60 * 60;
     let duration = 1 * 24 * 60 * 60;
 
-    set_lien.lease(&leaser, &token, &price, &max_duration);
+    set_lien.lease(&leaser, &token, &payment, &price, &max_duration, &no_terms(&e, &payment));
 
     // Verify fields
     let lease = set_lien.get_lease(&token).unwrap();
@@ -390,7 +421,12 @@ fn test_claim() {
     // assert_eq!(false, token_client.authorized(&renter));
 
     assert_eq!(0 as i128, payment_client.balance(&renter));
-    assert_eq!(price as i128, payment_client.balance(&leaser));
+    // payment is held in escrow until it is earned
+    assert_eq!(0 as i128, payment_client.balance(&leaser));
+    assert_eq!(price as i128, payment_client.balance(&set_lien.address));
+
+    // rental is overdue
+    e.ledger().with_mut(|li| li.timestamp = max_duration as u64);
 
     set_lien.claim_token(&leaser, &token, &false);
     let has_lease = set_lien.has_lease(&token);
@@ -408,3 +444,1787 @@ fn test_claim() {
 
 }
 
+
+const DAY: u128 = 24 * 60 * 60;
+
+struct Setup<'a> {
+    admin: Address,
+    leaser: Address,
+    renter: Address,
+    token_client: token::Client<'a>,
+    payment_client: token::Client<'a>,
+    set_lien: SetLienClient<'a>,
+}
+
+fn setup<'a>(e: &Env) -> Setup<'a> {
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(e);
+    let leaser = Address::generate(e);
+    let renter = Address::generate(e);
+
+    let token_client = create_token(e, &leaser);
+    token_client.mint(&leaser, &1);
+
+    let payment_client = create_token(e, &admin);
+    payment_client.mint(&renter, &1000);
+
+    let set_lien = create_setlien(e, &admin, &payment_client.address);
+
+    token_client.set_admin(&set_lien.address);
+    set_lien.approve_collection(&token_client.address);
+    token_client.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    payment_client.approve(&renter, &set_lien.address, &1000, &EXPIRATION_LEDGER);
+
+    Setup { admin, leaser, renter, token_client, payment_client, set_lien }
+}
+
+fn create_nft<'a>(e: &Env, s: &Setup, owner: &Address) -> token::Client<'a> {
+    let nft = create_token(e, &s.set_lien.address);
+    nft.mint(owner, &1);
+    s.set_lien.approve_collection(&nft.address);
+    nft.approve(owner, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    nft
+}
+
+fn no_terms(e: &Env, payment_token: &Address) -> LeaseTerms {
+    LeaseTerms {
+        grace_period: 0,
+        collateral: Collateral { token: payment_token.clone(), amount: 0 },
+        access: RenterAccess::Public,
+        payees: vec![e],
+    }
+}
+
+// Queues `action` and runs it once the timelock has passed, leaving the ledger time as it was
+fn run_admin_action(e: &Env, s: &Setup, action: AdminAction) {
+    let id = s.set_lien.queue_action(&action);
+    let now = e.ledger().timestamp();
+    let eta = s.set_lien.get_queued_action(&id).unwrap().eta;
+    e.ledger().with_mut(|li| li.timestamp = eta as u64);
+    s.set_lien.execute_action(&id);
+    e.ledger().with_mut(|li| li.timestamp = now);
+}
+
+#[test]
+fn test_initialize_twice() {
+    let e = Env::default();
+    let s = setup(&e);
+
+    assert_eq!(
+        s.set_lien.try_initialize(&s.admin, &s.payment_client.address),
+        Err(Ok(LienError::AlreadyInitialized))
+    );
+}
+
+#[test]
+fn test_lease_errors() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &0, &(30 * DAY), &no_terms(&e, &s.payment_client.address)),
+        Err(Ok(LienError::NotLeaseable))
+    );
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &10, &0, &no_terms(&e, &s.payment_client.address)),
+        Err(Ok(LienError::NotLeaseable))
+    );
+    assert_eq!(
+        s.set_lien.try_lease(&s.renter, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address)),
+        Err(Ok(LienError::InsufficientBalance))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address)),
+        Err(Ok(LienError::LeaseAlreadyExists))
+    );
+}
+
+#[test]
+fn test_rent_errors() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(
+        s.set_lien.try_rent(&s.renter, &token, &DAY),
+        Err(Ok(LienError::LeaseNotFound))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    // leaser cannot rent their own token
+    assert_eq!(
+        s.set_lien.try_rent(&s.leaser, &token, &DAY),
+        Err(Ok(LienError::NotRentable))
+    );
+    // partial days are not rentable
+    assert_eq!(
+        s.set_lien.try_rent(&s.renter, &token, &(DAY + 1)),
+        Err(Ok(LienError::NotRentable))
+    );
+    // longer than the listing allows
+    assert_eq!(
+        s.set_lien.try_rent(&s.renter, &token, &(31 * DAY)),
+        Err(Ok(LienError::NotRentable))
+    );
+}
+
+#[test]
+fn test_state_errors() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(
+        s.set_lien.try_end_lease(&s.leaser, &token),
+        Err(Ok(LienError::LeaseNotFound))
+    );
+    assert_eq!(
+        s.set_lien.try_end_rent(&s.renter, &token),
+        Err(Ok(LienError::LeaseNotFound))
+    );
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::LeaseNotFound))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_end_rent(&s.renter, &token),
+        Err(Ok(LienError::NotRented))
+    );
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::NotRented))
+    );
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(
+        s.set_lien.try_end_lease(&s.leaser, &token),
+        Err(Ok(LienError::NotListed))
+    );
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::NotClaimable))
+    );
+}
+
+#[test]
+fn test_pause_blocks_lease_and_rent() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(false, s.set_lien.is_paused());
+    s.set_lien.pause();
+    assert_eq!(true, s.set_lien.is_paused());
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address)),
+        Err(Ok(LienError::Paused))
+    );
+
+    s.set_lien.resume();
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    s.set_lien.pause();
+    assert_eq!(
+        s.set_lien.try_rent(&s.renter, &token, &DAY),
+        Err(Ok(LienError::Paused))
+    );
+
+    // delisting is still possible while paused
+    s.set_lien.end_lease(&s.leaser, &token);
+    assert_eq!(false, s.set_lien.has_lease(&token));
+}
+
+#[test]
+fn test_pause_allows_returns() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    s.set_lien.pause();
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+
+    assert_eq!(false, s.set_lien.has_lease(&token));
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+}
+
+#[test]
+fn test_pause_per_entry_point() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(true, s.set_lien.is_pausable(&EntryPoint::Lease));
+    assert_eq!(true, s.set_lien.is_pausable(&EntryPoint::Rent));
+    assert_eq!(false, s.set_lien.is_pausable(&EntryPoint::EndLease));
+    assert_eq!(false, s.set_lien.is_pausable(&EntryPoint::EndRent));
+    assert_eq!(false, s.set_lien.is_pausable(&EntryPoint::Claim));
+
+    s.set_lien.set_pausable(&EntryPoint::Lease, &false);
+    s.set_lien.set_pausable(&EntryPoint::Claim, &true);
+    s.set_lien.pause();
+
+    // leasing is exempt from the pause now
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    s.set_lien.resume();
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    e.ledger().with_mut(|li| li.timestamp = (30 * DAY) as u64);
+
+    s.set_lien.pause();
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::Paused))
+    );
+
+    s.set_lien.resume();
+    s.set_lien.claim_token(&s.leaser, &token, &false);
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+}
+
+#[test]
+fn test_escrow_refund_on_early_return() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &(10 * DAY));
+
+    let escrow = s.set_lien.get_escrow(&token).unwrap();
+    assert_eq!(100, escrow.amount);
+    assert_eq!(0, escrow.withdrawn);
+    assert_eq!(900, s.payment_client.balance(&s.renter));
+    assert_eq!(100, s.payment_client.balance(&s.set_lien.address));
+
+    // return during the third day
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY + 1) as u64);
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+
+    assert_eq!(30, s.payment_client.balance(&s.leaser));
+    assert_eq!(970, s.payment_client.balance(&s.renter));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+    assert!(s.set_lien.get_escrow(&token).is_none());
+}
+
+#[test]
+fn test_withdraw_earnings() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_withdraw_earnings(&token),
+        Err(Ok(LienError::NotRented))
+    );
+
+    s.set_lien.rent(&s.renter, &token, &(10 * DAY));
+
+    // first day has started
+    assert_eq!(10, s.set_lien.get_earnings(&token));
+    assert_eq!(10, s.set_lien.withdraw_earnings(&token));
+    assert_eq!(10, s.payment_client.balance(&s.leaser));
+    assert_eq!(
+        s.set_lien.try_withdraw_earnings(&token),
+        Err(Ok(LienError::NothingToWithdraw))
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = (4 * DAY) as u64);
+    assert_eq!(40, s.set_lien.get_earnings(&token));
+    assert_eq!(40, s.set_lien.withdraw_earnings(&token));
+    assert_eq!(50, s.set_lien.get_escrow(&token).unwrap().withdrawn);
+
+    // only the remaining days are refunded on return
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(50, s.payment_client.balance(&s.leaser));
+    assert_eq!(950, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_claim_releases_escrow() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &(3 * DAY));
+    s.set_lien.withdraw_earnings(&token);
+
+    e.ledger().with_mut(|li| li.timestamp = (30 * DAY) as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &false);
+
+    assert_eq!(30, s.payment_client.balance(&s.leaser));
+    assert_eq!(970, s.payment_client.balance(&s.renter));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+}
+
+#[test]
+fn test_fee_accrues_without_treasury() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+
+    assert_eq!(s.set_lien.try_queue_action(&AdminAction::SetFee(10_001)), Err(Ok(LienError::InvalidFee)));
+    run_admin_action(&e, &s, AdminAction::SetFee(250));
+    assert_eq!(250, s.set_lien.get_fee());
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &100, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+
+    // 2.5% of 200
+    assert_eq!(5, s.set_lien.get_accrued_fees(&payment));
+    assert_eq!(195, s.set_lien.get_escrow(&token).unwrap().amount);
+    assert_eq!(200, s.payment_client.balance(&s.set_lien.address));
+
+    let treasury = Address::generate(&e);
+    assert_eq!(5, s.set_lien.withdraw_fees(&payment, &treasury));
+    assert_eq!(5, s.payment_client.balance(&treasury));
+    assert_eq!(0, s.set_lien.get_accrued_fees(&payment));
+    assert_eq!(
+        s.set_lien.try_withdraw_fees(&payment, &treasury),
+        Err(Ok(LienError::NothingToWithdraw))
+    );
+
+    // fee is not refunded on early return
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(97, s.payment_client.balance(&s.leaser));
+    assert_eq!(898, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_fee_paid_to_treasury() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let treasury = Address::generate(&e);
+
+    run_admin_action(&e, &s, AdminAction::SetFee(1_000));
+    s.set_lien.set_treasury(&Some(treasury.clone()));
+    assert_eq!(Some(treasury.clone()), s.set_lien.get_treasury());
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &100, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    assert_eq!(10, s.payment_client.balance(&treasury));
+    assert_eq!(0, s.set_lien.get_accrued_fees(&s.payment_client.address));
+    assert_eq!(90, s.set_lien.get_escrow(&token).unwrap().amount);
+
+    s.set_lien.set_treasury(&None);
+    assert_eq!(None, s.set_lien.get_treasury());
+}
+
+#[test]
+fn test_listing_payment_token() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    let eurc = create_token(&e, &s.admin);
+    eurc.mint(&s.renter, &1000);
+    eurc.approve(&s.renter, &s.set_lien.address, &1000, &EXPIRATION_LEDGER);
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &eurc.address, &10, &(30 * DAY), &no_terms(&e, &eurc.address)),
+        Err(Ok(LienError::PaymentTokenNotAccepted))
+    );
+
+    s.set_lien.add_payment_token(&eurc.address);
+    assert_eq!(2, s.set_lien.get_payment_tokens().len());
+
+    s.set_lien.lease(&s.leaser, &token, &eurc.address, &10, &(30 * DAY), &no_terms(&e, &eurc.address));
+    assert_eq!(eurc.address, s.set_lien.get_lease(&token).unwrap().leasing.payment_token);
+
+    // neither the allowlist nor the default token reprice the listing
+    s.set_lien.remove_payment_token(&eurc.address);
+    run_admin_action(&e, &s, AdminAction::ChangePaymentToken(Address::generate(&e)));
+    assert_eq!(false, s.set_lien.get_payment_tokens().contains(&eurc.address));
+
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+    assert_eq!(980, eurc.balance(&s.renter));
+    assert_eq!(1000, s.payment_client.balance(&s.renter));
+    assert_eq!(eurc.address, s.set_lien.get_escrow(&token).unwrap().payment_token);
+
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(10, eurc.balance(&s.leaser));
+    assert_eq!(990, eurc.balance(&s.renter));
+}
+
+#[test]
+fn test_lease_requires_nft() {
+    let e = Env::default();
+    let s = setup(&e);
+    let payment = s.payment_client.address.clone();
+
+    // a fungible asset that was never approved as a collection
+    let fungible = create_token(&e, &s.set_lien.address);
+    fungible.mint(&s.leaser, &1);
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &fungible.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::NotAnNft))
+    );
+
+    // approved, but SetLien is not the asset admin
+    let foreign = create_token(&e, &s.admin);
+    foreign.mint(&s.leaser, &1);
+    s.set_lien.approve_collection(&foreign.address);
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &foreign.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::NotTokenAdmin))
+    );
+
+    // zero decimals pass without approval, but this contract has no admin
+    let custom = token::Client::new(&e, &e.register_contract_wasm(None, token::WASM));
+    custom.initialize(&s.admin, &0, &"name".into_val(&e), &"symbol".into_val(&e));
+    custom.mint(&s.leaser, &1);
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &custom.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::NotTokenAdmin))
+    );
+
+    // more than a single unit
+    let edition = create_token(&e, &s.set_lien.address);
+    edition.mint(&s.leaser, &2);
+    s.set_lien.approve_collection(&edition.address);
+    assert_eq!(true, s.set_lien.is_collection_approved(&edition.address));
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &edition.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::InvalidNftBalance))
+    );
+
+    s.set_lien.revoke_collection(&edition.address);
+    assert_eq!(false, s.set_lien.is_collection_approved(&edition.address));
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &edition.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::NotAnNft))
+    );
+}
+
+#[test]
+fn test_claim_uses_rent_duration() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    e.ledger().with_mut(|li| li.timestamp = 1_000);
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(None, s.set_lien.claimable_at(&token));
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(Some(1_000 + DAY), s.set_lien.claimable_at(&token));
+
+    // one second before the rental ends
+    e.ledger().with_mut(|li| li.timestamp = (1_000 + DAY - 1) as u64);
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::NotClaimable))
+    );
+
+    // claimable as soon as the rental ends, long before the listing's maximum
+    e.ledger().with_mut(|li| li.timestamp = (1_000 + DAY) as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &false);
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+    assert_eq!(None, s.set_lien.claimable_at(&token));
+}
+
+#[test]
+fn test_claim_grace_period() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let grace_period = 6 * 60 * 60;
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &LeaseTerms { grace_period: 31 * DAY, ..no_terms(&e, &s.payment_client.address) }),
+        Err(Ok(LienError::InvalidGracePeriod))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &LeaseTerms { grace_period, ..no_terms(&e, &s.payment_client.address) });
+    assert_eq!(grace_period, s.set_lien.get_lease(&token).unwrap().leasing.grace_period);
+
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+    assert_eq!(Some(2 * DAY + grace_period), s.set_lien.claimable_at(&token));
+
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY) as u64);
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &false),
+        Err(Ok(LienError::NotClaimable))
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY + grace_period - 1) as u64);
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.leaser, &token, &true),
+        Err(Ok(LienError::NotClaimable))
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY + grace_period) as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &true);
+
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(LeaseState::Listed, lease.state);
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+}
+
+#[test]
+fn test_extend_rent() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(5 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_extend_rent(&s.renter, &token, &DAY),
+        Err(Ok(LienError::NotRented))
+    );
+
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+    s.set_lien.extend_rent(&s.renter, &token, &(2 * DAY));
+
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(4 * DAY, lease.renting.rent_duration);
+    assert_eq!(0, lease.renting.rented_at);
+    assert_eq!(1, s.token_client.balance(&s.renter));
+    assert_eq!(40, s.set_lien.get_escrow(&token).unwrap().amount);
+    assert_eq!(960, s.payment_client.balance(&s.renter));
+    assert_eq!(Some(4 * DAY), s.set_lien.claimable_at(&token));
+
+    // past the listing's maximum
+    assert_eq!(
+        s.set_lien.try_extend_rent(&s.renter, &token, &(2 * DAY)),
+        Err(Ok(LienError::NotRentable))
+    );
+    // partial days
+    assert_eq!(
+        s.set_lien.try_extend_rent(&s.renter, &token, &(DAY / 2)),
+        Err(Ok(LienError::NotRentable))
+    );
+    // only the current renter can extend
+    assert_eq!(
+        s.set_lien.try_extend_rent(&s.leaser, &token, &DAY),
+        Err(Ok(LienError::NotRenter))
+    );
+
+    s.set_lien.extend_rent(&s.renter, &token, &DAY);
+    assert_eq!(5 * DAY, s.set_lien.get_lease(&token).unwrap().renting.rent_duration);
+    assert_eq!(950, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_extend_rent_after_withdrawal() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+    run_admin_action(&e, &s, AdminAction::SetFee(100));
+
+    s.set_lien.lease(&s.leaser, &token, &payment, &150, &(5 * DAY), &no_terms(&e, &payment));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(149, s.set_lien.withdraw_earnings(&token));
+
+    // The extension's fee rounds separately, the leaser is owed nothing more yet
+    s.set_lien.extend_rent(&s.renter, &token, &(2 * DAY));
+    assert_eq!(446, s.set_lien.get_escrow(&token).unwrap().amount);
+    assert_eq!(0, s.set_lien.get_earnings(&token));
+
+    // Returning now refunds the whole extension
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(149, s.payment_client.balance(&s.leaser));
+    assert_eq!(847, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_update_lease() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    assert_eq!(
+        s.set_lien.try_update_lease(&s.leaser, &token, &20, &(10 * DAY)),
+        Err(Ok(LienError::LeaseNotFound))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.update_lease(&s.leaser, &token, &20, &(10 * DAY));
+
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(LeaseState::Listed, lease.state);
+    assert_eq!(20, lease.leasing.price);
+    assert_eq!(10 * DAY, lease.leasing.max_duration);
+    assert_eq!(s.token_client.address, s.set_lien.get_all_listed().get(0).unwrap());
+    assert_eq!(false, StellarAssetClient::new(&e, &token).authorized(&s.leaser));
+
+    assert_eq!(
+        s.set_lien.try_update_lease(&s.renter, &token, &20, &(10 * DAY)),
+        Err(Ok(LienError::NotLeaser))
+    );
+    assert_eq!(
+        s.set_lien.try_update_lease(&s.leaser, &token, &0, &(10 * DAY)),
+        Err(Ok(LienError::NotLeaseable))
+    );
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(980, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_update_lease_while_rented() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    s.set_lien.update_lease(&s.leaser, &token, &50, &(2 * DAY));
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(LeaseState::Rented, lease.state);
+    assert_eq!(50, lease.leasing.price);
+    assert_eq!(10, lease.renting.price);
+
+    // the running rental keeps its terms
+    s.set_lien.extend_rent(&s.renter, &token, &(4 * DAY));
+    assert_eq!(950, s.payment_client.balance(&s.renter));
+
+    // the next rental uses the new ones
+    e.ledger().with_mut(|li| li.timestamp = (5 * DAY) as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &true);
+
+    let renter = Address::generate(&e);
+    s.payment_client.mint(&renter, &100);
+    s.payment_client.approve(&renter, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    assert_eq!(
+        s.set_lien.try_rent(&renter, &token, &(3 * DAY)),
+        Err(Ok(LienError::NotRentable))
+    );
+    s.set_lien.rent(&renter, &token, &(2 * DAY));
+    assert_eq!(0, s.payment_client.balance(&renter));
+}
+
+#[test]
+fn test_listings_index() {
+    let e = Env::default();
+    let s = setup(&e);
+    let payment = s.payment_client.address.clone();
+
+    let mut tokens = std::vec::Vec::new();
+    for _ in 0..5 {
+        let nft = create_nft(&e, &s, &s.leaser);
+        s.set_lien.lease(&s.leaser, &nft.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment));
+        tokens.push(nft.address.clone());
+    }
+
+    assert_eq!(5, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Rented));
+
+    let page = s.set_lien.get_listings(&LeaseState::Listed, &1, &2);
+    assert_eq!(2, page.len());
+    assert_eq!(tokens[1], page.get(0).unwrap());
+    assert_eq!(tokens[2], page.get(1).unwrap());
+    assert_eq!(1, s.set_lien.get_listings(&LeaseState::Listed, &4, &10).len());
+    assert_eq!(0, s.set_lien.get_listings(&LeaseState::Listed, &10, &10).len());
+
+    // renting moves the token to the rented index
+    s.set_lien.rent(&s.renter, &tokens[1], &DAY);
+    assert_eq!(4, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Rented));
+    assert_eq!(tokens[1], s.set_lien.get_listings(&LeaseState::Rented, &0, &10).get(0).unwrap());
+    assert_eq!(false, s.set_lien.get_all_listed().contains(&tokens[1]));
+
+    // removing fills the gap with the last listing
+    let listed = s.set_lien.get_listings(&LeaseState::Listed, &0, &10);
+    assert_eq!(tokens[4], listed.get(1).unwrap());
+
+    s.set_lien.end_lease(&s.leaser, &tokens[0]);
+    let listed = s.set_lien.get_listings(&LeaseState::Listed, &0, &10);
+    assert_eq!(3, listed.len());
+    assert_eq!(tokens[3], listed.get(0).unwrap());
+    assert_eq!(tokens[4], listed.get(1).unwrap());
+    assert_eq!(tokens[2], listed.get(2).unwrap());
+
+    s.set_lien.end_lease(&s.leaser, &tokens[3]);
+    s.set_lien.end_lease(&s.leaser, &tokens[4]);
+    assert_eq!(tokens[2], s.set_lien.get_all_listed().get(0).unwrap());
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Listed));
+
+    // returning removes it entirely
+    let nft = token::Client::new(&e, &tokens[1]);
+    nft.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &tokens[1]);
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Rented));
+    assert_eq!(s.set_lien.try_get_listing_count(&LeaseState::Available), Err(Ok(LienError::UnindexedState)));
+    assert_eq!(s.set_lien.try_get_listings(&LeaseState::Available, &0, &10), Err(Ok(LienError::UnindexedState)));
+}
+
+#[test]
+fn test_listings_index_claim_relist() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &true);
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Rented));
+}
+
+#[test]
+fn test_third_party_calls() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let stranger = Address::generate(&e);
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    assert_eq!(
+        s.set_lien.try_end_lease(&stranger, &token),
+        Err(Ok(LienError::NotLeaser))
+    );
+    assert_eq!(
+        s.set_lien.try_update_lease(&stranger, &token, &1, &DAY),
+        Err(Ok(LienError::NotLeaser))
+    );
+    // the stranger did not get their address authorized on the nft
+    let nft_admin = StellarAssetClient::new(&e, &token);
+    assert_eq!(false, nft_admin.authorized(&s.leaser));
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Listed));
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    s.payment_client.mint(&stranger, &100);
+    s.payment_client.approve(&stranger, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+    assert_eq!(
+        s.set_lien.try_rent(&stranger, &token, &DAY),
+        Err(Ok(LienError::NotListed))
+    );
+    assert_eq!(
+        s.set_lien.try_end_rent(&stranger, &token),
+        Err(Ok(LienError::NotRenter))
+    );
+    assert_eq!(
+        s.set_lien.try_extend_rent(&stranger, &token, &DAY),
+        Err(Ok(LienError::NotRenter))
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    assert_eq!(
+        s.set_lien.try_claim_token(&stranger, &token, &false),
+        Err(Ok(LienError::NotLeaser))
+    );
+    assert_eq!(
+        s.set_lien.try_claim_token(&s.renter, &token, &false),
+        Err(Ok(LienError::NotLeaser))
+    );
+
+    assert_eq!(1, s.token_client.balance(&s.renter));
+    assert_eq!(LeaseState::Rented, s.set_lien.get_lease(&token).unwrap().state);
+    assert_eq!(100, s.payment_client.balance(&stranger));
+}
+
+#[test]
+fn test_collateral_returned() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+    let collateral = Collateral { token: payment.clone(), amount: 500 };
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { collateral: Collateral { token: token.clone(), amount: 1 }, ..no_terms(&e, &payment) }),
+        Err(Ok(LienError::PaymentTokenNotAccepted))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { collateral: collateral.clone(), ..no_terms(&e, &payment) });
+    assert_eq!(collateral, s.set_lien.get_lease(&token).unwrap().leasing.collateral);
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(collateral, s.set_lien.get_lease(&token).unwrap().renting.collateral);
+    assert_eq!(490, s.payment_client.balance(&s.renter));
+    assert_eq!(510, s.payment_client.balance(&s.set_lien.address));
+
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(990, s.payment_client.balance(&s.renter));
+    assert_eq!(10, s.payment_client.balance(&s.leaser));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+}
+
+#[test]
+fn test_collateral_forfeited() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    let xlm = create_token(&e, &s.admin);
+    xlm.mint(&s.renter, &300);
+    xlm.approve(&s.renter, &s.set_lien.address, &300, &EXPIRATION_LEDGER);
+    s.set_lien.add_payment_token(&xlm.address);
+    let collateral = Collateral { token: xlm.address.clone(), amount: 300 };
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &LeaseTerms { collateral, ..no_terms(&e, &s.payment_client.address) });
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(0, xlm.balance(&s.renter));
+
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    s.set_lien.claim_token(&s.leaser, &token, &true);
+
+    assert_eq!(300, xlm.balance(&s.leaser));
+    assert_eq!(0, xlm.balance(&s.set_lien.address));
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(300, lease.leasing.collateral.amount);
+    assert_eq!(0, lease.renting.collateral.amount);
+}
+
+#[test]
+fn test_offer_accepted() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    assert_eq!(
+        s.set_lien.try_make_offer(&s.renter, &token, &0, &(2 * DAY), &DAY),
+        Err(Ok(LienError::NotRentable))
+    );
+    assert_eq!(
+        s.set_lien.try_make_offer(&s.renter, &token, &5, &(31 * DAY), &DAY),
+        Err(Ok(LienError::NotRentable))
+    );
+    assert_eq!(
+        s.set_lien.try_make_offer(&s.renter, &token, &5, &(2 * DAY), &0),
+        Err(Ok(LienError::OfferExpired))
+    );
+
+    s.set_lien.make_offer(&s.renter, &token, &5, &(2 * DAY), &DAY);
+    assert_eq!(990, s.payment_client.balance(&s.renter));
+    assert_eq!(
+        s.set_lien.try_make_offer(&s.renter, &token, &5, &(2 * DAY), &DAY),
+        Err(Ok(LienError::OfferExists))
+    );
+    assert_eq!(vec![&e, s.renter.clone()], s.set_lien.get_offers_by_token(&token));
+    assert_eq!(vec![&e, token.clone()], s.set_lien.get_offers_by_renter(&s.renter));
+
+    assert_eq!(
+        s.set_lien.try_accept_offer(&s.renter, &token, &s.renter),
+        Err(Ok(LienError::NotLeaser))
+    );
+    s.set_lien.accept_offer(&s.leaser, &token, &s.renter);
+
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(LeaseState::Rented, lease.state);
+    assert_eq!(5, lease.renting.price);
+    assert_eq!(2 * DAY, lease.renting.rent_duration);
+    assert_eq!(1, s.token_client.balance(&s.renter));
+    assert_eq!(10, s.set_lien.get_escrow(&token).unwrap().amount);
+    assert!(s.set_lien.get_offer(&token, &s.renter).is_none());
+    assert_eq!(0, s.set_lien.get_offers_by_token(&token).len());
+    assert_eq!(0, s.set_lien.get_offers_by_renter(&s.renter).len());
+}
+
+#[test]
+fn test_offer_rejected_and_cancelled() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let other = Address::generate(&e);
+    s.payment_client.mint(&other, &100);
+    s.payment_client.approve(&other, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.make_offer(&s.renter, &token, &5, &(2 * DAY), &DAY);
+    s.set_lien.make_offer(&other, &token, &8, &DAY, &DAY);
+    assert_eq!(2, s.set_lien.get_offers_by_token(&token).len());
+
+    s.set_lien.reject_offer(&s.leaser, &token, &s.renter);
+    assert_eq!(1000, s.payment_client.balance(&s.renter));
+    assert_eq!(
+        s.set_lien.try_reject_offer(&s.leaser, &token, &s.renter),
+        Err(Ok(LienError::OfferNotFound))
+    );
+
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    assert_eq!(
+        s.set_lien.try_accept_offer(&s.leaser, &token, &other),
+        Err(Ok(LienError::OfferExpired))
+    );
+
+    // Closing the listing refunds the offers still open on it
+    s.set_lien.end_lease(&s.leaser, &token);
+    assert_eq!(100, s.payment_client.balance(&other));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+    assert_eq!(0, s.set_lien.get_offers_by_token(&token).len());
+    assert_eq!(0, s.set_lien.get_offers_by_renter(&other).len());
+    assert_eq!(s.set_lien.try_cancel_offer(&other, &token), Err(Ok(LienError::OfferNotFound)));
+}
+
+#[test]
+fn test_offer_does_not_outlive_listing() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let second = create_nft(&e, &s, &s.leaser).address;
+    let other = Address::generate(&e);
+    let eurc = create_token(&e, &s.admin);
+    s.set_lien.add_payment_token(&eurc.address);
+    for renter in [&s.renter, &other] {
+        eurc.mint(renter, &100);
+        eurc.approve(renter, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+    }
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.make_offer(&s.renter, &token, &25, &(2 * DAY), &(5 * DAY));
+    assert_eq!(950, s.payment_client.balance(&s.renter));
+
+    // Someone else's rental holds eurc in the contract
+    s.set_lien.lease(&s.leaser, &second, &eurc.address, &10, &(30 * DAY), &no_terms(&e, &eurc.address));
+    s.set_lien.rent(&other, &second, &(5 * DAY));
+    assert_eq!(50, eurc.balance(&s.set_lien.address));
+
+    s.set_lien.end_lease(&s.leaser, &token);
+    assert_eq!(1000, s.payment_client.balance(&s.renter));
+
+    // Relisted in eurc, the old offer cannot be accepted against it
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.lease(&s.leaser, &token, &eurc.address, &10, &(30 * DAY), &no_terms(&e, &eurc.address));
+    assert_eq!(
+        s.set_lien.try_accept_offer(&s.leaser, &token, &s.renter),
+        Err(Ok(LienError::OfferNotFound))
+    );
+    assert_eq!(50, eurc.balance(&s.set_lien.address));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+}
+
+#[test]
+fn test_offers_capped_per_token() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+
+    let renters = std::vec::Vec::from_iter((0..21).map(|_| Address::generate(&e)));
+    for renter in renters.iter() {
+        s.payment_client.mint(renter, &1);
+        s.payment_client.approve(renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    }
+    for renter in renters[..20].iter() {
+        s.set_lien.make_offer(renter, &token, &1, &DAY, &(5 * DAY));
+    }
+    assert_eq!(
+        s.set_lien.try_make_offer(&renters[20], &token, &1, &DAY, &(5 * DAY)),
+        Err(Ok(LienError::TooManyOffers))
+    );
+
+    // A full book is still refunded when the listing closes
+    s.set_lien.end_lease(&s.leaser, &token);
+    assert_eq!(0, s.set_lien.get_offers_by_token(&token).len());
+    assert_eq!(1, s.payment_client.balance(&renters[0]));
+}
+
+#[test]
+fn test_reservations() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let other = Address::generate(&e);
+    s.payment_client.mint(&other, &100);
+    s.payment_client.approve(&other, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.reserve(&s.renter, &token, &(2 * DAY), &(2 * DAY));
+    assert_eq!(980, s.payment_client.balance(&s.renter));
+
+    assert_eq!(
+        s.set_lien.try_reserve(&other, &token, &(3 * DAY), &DAY),
+        Err(Ok(LienError::ReservationConflict))
+    );
+    assert_eq!(
+        s.set_lien.try_rent(&other, &token, &(3 * DAY)),
+        Err(Ok(LienError::ReservationConflict))
+    );
+    s.set_lien.reserve(&other, &token, &(5 * DAY), &DAY);
+    let reservations = s.set_lien.get_reservations(&token);
+    assert_eq!(2, reservations.len());
+    assert_eq!(s.renter, reservations.get(0).unwrap().renter);
+
+    // A spot rental that ends before the first booking is allowed
+    s.set_lien.rent(&other, &token, &(2 * DAY));
+    assert_eq!(
+        s.set_lien.try_extend_rent(&other, &token, &DAY),
+        Err(Ok(LienError::ReservationConflict))
+    );
+    s.token_client.approve(&other, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&other, &token);
+    assert_eq!(LeaseState::Listed, s.set_lien.get_lease(&token).unwrap().state);
+
+    assert_eq!(
+        s.set_lien.try_activate_reservation(&token),
+        Err(Ok(LienError::ReservationNotStarted))
+    );
+    e.ledger().with_mut(|li| li.timestamp = 2 * DAY as u64);
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.activate_reservation(&token);
+
+    let lease = s.set_lien.get_lease(&token).unwrap();
+    assert_eq!(LeaseState::Rented, lease.state);
+    assert_eq!(s.renter, lease.renting.renter);
+    assert_eq!(2 * DAY, lease.renting.rented_at);
+    assert_eq!(1, s.token_client.balance(&s.renter));
+    assert_eq!(1, s.set_lien.get_reservations(&token).len());
+}
+
+#[test]
+fn test_reservation_cancellation() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_set_cancellation_policy(&s.leaser, &token, &(2 * DAY), &10_001),
+        Err(Ok(LienError::InvalidPolicy))
+    );
+    s.set_lien.set_cancellation_policy(&s.leaser, &token, &(2 * DAY), &5_000);
+
+    // Cancelling ahead of the notice period is free
+    s.set_lien.reserve(&s.renter, &token, &(3 * DAY), &DAY);
+    assert_eq!(0, s.set_lien.cancel_reservation(&s.renter, &token, &(3 * DAY)));
+    assert_eq!(1000, s.payment_client.balance(&s.renter));
+
+    s.set_lien.reserve(&s.renter, &token, &(3 * DAY), &DAY);
+    assert_eq!(
+        s.set_lien.try_cancel_reservation(&s.leaser, &token, &(3 * DAY)),
+        Err(Ok(LienError::NotRenter))
+    );
+    e.ledger().with_mut(|li| li.timestamp = 2 * DAY as u64);
+    assert_eq!(5, s.set_lien.cancel_reservation(&s.renter, &token, &(3 * DAY)));
+    assert_eq!(995, s.payment_client.balance(&s.renter));
+    assert_eq!(5, s.payment_client.balance(&s.leaser));
+    assert_eq!(
+        s.set_lien.try_cancel_reservation(&s.renter, &token, &(3 * DAY)),
+        Err(Ok(LienError::ReservationNotFound))
+    );
+
+    // Delisting refunds outstanding bookings in full
+    s.set_lien.reserve(&s.renter, &token, &(4 * DAY), &DAY);
+    s.set_lien.end_lease(&s.leaser, &token);
+    assert_eq!(995, s.payment_client.balance(&s.renter));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+    assert_eq!(0, s.set_lien.get_reservations(&token).len());
+}
+
+#[test]
+fn test_tiered_pricing() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    run_admin_action(&e, &s, AdminAction::SetFee(1_000));
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(60 * DAY), &no_terms(&e, &s.payment_client.address));
+    let tiers = vec![
+        &e,
+        PriceTier { min_duration: 7 * DAY, price: 8 },
+        PriceTier { min_duration: 30 * DAY, price: 5 },
+    ];
+
+    assert_eq!(
+        s.set_lien.try_set_pricing(&s.leaser, &token, &vec![&e, PriceTier { min_duration: 7 * DAY, price: 0 }], &0),
+        Err(Ok(LienError::InvalidPricing))
+    );
+    assert_eq!(
+        s.set_lien.try_set_pricing(&s.leaser, &token, &vec![&e, tiers.get(1).unwrap(), tiers.get(0).unwrap()], &0),
+        Err(Ok(LienError::InvalidPricing))
+    );
+    assert_eq!(
+        s.set_lien.try_set_pricing(&s.leaser, &token, &tiers, &(61 * DAY)),
+        Err(Ok(LienError::InvalidPricing))
+    );
+    s.set_lien.set_pricing(&s.leaser, &token, &tiers, &(2 * DAY));
+
+    assert_eq!(s.set_lien.try_quote_rent(&token, &DAY), Err(Ok(LienError::NotRentable)));
+    assert_eq!(30, s.set_lien.quote_rent(&token, &(3 * DAY)).price);
+    assert_eq!(56, s.set_lien.quote_rent(&token, &(7 * DAY)).price);
+    let quote = s.set_lien.quote_rent(&token, &(30 * DAY));
+    assert_eq!(30, quote.units);
+    assert_eq!(5, quote.price_per_unit);
+    assert_eq!(150, quote.price);
+    assert_eq!(15, quote.fee);
+
+    assert_eq!(s.set_lien.try_rent(&s.renter, &token, &DAY), Err(Ok(LienError::NotRentable)));
+    s.set_lien.rent(&s.renter, &token, &(30 * DAY));
+    assert_eq!(850, s.payment_client.balance(&s.renter));
+    assert_eq!(5, s.set_lien.get_lease(&token).unwrap().renting.price);
+    assert_eq!(135, s.set_lien.get_escrow(&token).unwrap().amount);
+
+    // Extensions keep the daily price the rental was agreed on
+    s.set_lien.extend_rent(&s.renter, &token, &DAY);
+    assert_eq!(845, s.payment_client.balance(&s.renter));
+}
+
+#[test]
+fn test_hourly_rental() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let hour = 3600;
+
+    assert_eq!(TimeUnit::Day, s.set_lien.get_time_unit());
+    s.set_lien.set_time_unit(&TimeUnit::Hour);
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &2, &(24 * hour), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(TimeUnit::Hour, s.set_lien.get_lease(&token).unwrap().leasing.unit);
+
+    assert_eq!(
+        s.set_lien.try_rent(&s.renter, &token, &(hour / 2)),
+        Err(Ok(LienError::NotRentable))
+    );
+    s.set_lien.rent(&s.renter, &token, &(5 * hour));
+    assert_eq!(990, s.payment_client.balance(&s.renter));
+    assert_eq!(5 * hour, s.set_lien.claimable_at(&token).unwrap());
+
+    // Two started hours are earned, the rest is refunded
+    e.ledger().with_mut(|li| li.timestamp = (hour + 1) as u64);
+    assert_eq!(4, s.set_lien.get_earnings(&token));
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(996, s.payment_client.balance(&s.renter));
+    assert_eq!(4, s.payment_client.balance(&s.leaser));
+
+    // Listings keep the unit they were created with
+    s.set_lien.set_time_unit(&TimeUnit::Week);
+    let token = create_nft(&e, &s, &s.leaser).address;
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &2, &(24 * hour), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(TimeUnit::Week, s.set_lien.get_lease(&token).unwrap().leasing.unit);
+}
+
+#[test]
+fn test_amount_overflow() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { collateral: Collateral { token: payment.clone(), amount: u128::MAX }, ..no_terms(&e, &payment) }),
+        Err(Ok(LienError::AmountOverflow))
+    );
+
+    s.set_lien.lease(&s.leaser, &token, &payment, &u128::MAX, &(30 * DAY), &no_terms(&e, &payment));
+    assert_eq!(s.set_lien.try_quote_rent(&token, &(2 * DAY)), Err(Ok(LienError::AmountOverflow)));
+    assert_eq!(s.set_lien.try_rent(&s.renter, &token, &(2 * DAY)), Err(Ok(LienError::AmountOverflow)));
+    assert_eq!(s.set_lien.try_rent(&s.renter, &token, &DAY), Err(Ok(LienError::AmountOverflow)));
+}
+
+#[test]
+fn test_duration_overflow() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+
+    s.set_lien.lease(&s.leaser, &token, &payment, &1, &u128::MAX, &LeaseTerms { grace_period: 30 * DAY, ..no_terms(&e, &payment) });
+    assert_eq!(
+        s.set_lien.try_reserve(&s.renter, &token, &(u128::MAX - DAY + 1), &DAY),
+        Err(Ok(LienError::NotRentable))
+    );
+
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(Some(31 * DAY), s.set_lien.claimable_at(&token));
+    assert_eq!(
+        s.set_lien.try_extend_rent(&s.renter, &token, &(u128::MAX / DAY * DAY)),
+        Err(Ok(LienError::NotRentable))
+    );
+}
+
+#[test]
+fn test_allowlisted_renters() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let other = Address::generate(&e);
+    s.payment_client.mint(&other, &100);
+    s.payment_client.approve(&other, &s.set_lien.address, &100, &EXPIRATION_LEDGER);
+
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &LeaseTerms { access: RenterAccess::Allowlist(vec![&e]), ..no_terms(&e, &s.payment_client.address) }),
+        Err(Ok(LienError::InvalidAccess))
+    );
+    let access = RenterAccess::Allowlist(vec![&e, other.clone()]);
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &LeaseTerms { access: access.clone(), ..no_terms(&e, &s.payment_client.address) });
+    assert_eq!(Some(access), s.set_lien.get_renter_access(&token));
+
+    assert_eq!(s.set_lien.try_rent(&s.renter, &token, &DAY), Err(Ok(LienError::NotAllowed)));
+    assert_eq!(
+        s.set_lien.try_make_offer(&s.renter, &token, &5, &DAY, &DAY),
+        Err(Ok(LienError::NotAllowed))
+    );
+    assert_eq!(
+        s.set_lien.try_reserve(&s.renter, &token, &DAY, &DAY),
+        Err(Ok(LienError::NotAllowed))
+    );
+
+    s.set_lien.add_allowed_renter(&s.leaser, &token, &s.renter);
+    s.set_lien.remove_allowed_renter(&s.leaser, &token, &other);
+    assert_eq!(
+        s.set_lien.try_remove_allowed_renter(&s.leaser, &token, &s.renter),
+        Err(Ok(LienError::InvalidAccess))
+    );
+    assert_eq!(
+        Some(RenterAccess::Allowlist(vec![&e, s.renter.clone()])),
+        s.set_lien.get_renter_access(&token)
+    );
+    assert_eq!(s.set_lien.try_rent(&other, &token, &DAY), Err(Ok(LienError::NotAllowed)));
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(None, s.set_lien.get_renter_access(&token));
+}
+
+#[test]
+fn test_holder_gated_listing() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let membership = create_token(&e, &s.admin);
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &10, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    assert_eq!(
+        s.set_lien.try_set_renter_access(&s.leaser, &token, &Some(RenterAccess::Holder(membership.address.clone(), 0))),
+        Err(Ok(LienError::InvalidAccess))
+    );
+    assert_eq!(
+        s.set_lien.try_set_renter_access(&s.renter, &token, &None),
+        Err(Ok(LienError::NotLeaser))
+    );
+    s.set_lien.set_renter_access(&s.leaser, &token, &Some(RenterAccess::Holder(membership.address.clone(), 5)));
+    assert_eq!(
+        s.set_lien.try_add_allowed_renter(&s.leaser, &token, &s.renter),
+        Err(Ok(LienError::InvalidAccess))
+    );
+
+    membership.mint(&s.renter, &4);
+    assert_eq!(s.set_lien.try_rent(&s.renter, &token, &DAY), Err(Ok(LienError::NotAllowed)));
+    membership.mint(&s.renter, &1);
+    s.set_lien.rent(&s.renter, &token, &DAY);
+    assert_eq!(LeaseState::Rented, s.set_lien.get_lease(&token).unwrap().state);
+}
+
+#[test]
+fn test_payees() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let manager = Address::generate(&e);
+    let payment = s.payment_client.address.clone();
+
+    let invalid = vec![
+        &e,
+        Payee { address: s.leaser.clone(), bps: 7_000 },
+        Payee { address: manager.clone(), bps: 2_000 },
+    ];
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { payees: invalid, ..no_terms(&e, &payment) }),
+        Err(Ok(LienError::InvalidPayees))
+    );
+
+    // Listing without payees pays the leaser everything
+    s.set_lien.lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &no_terms(&e, &payment));
+    assert_eq!(
+        vec![&e, Payee { address: s.leaser.clone(), bps: 10_000 }],
+        s.set_lien.get_lease(&token).unwrap().leasing.payees
+    );
+    s.set_lien.end_lease(&s.leaser, &token);
+
+    let payees = vec![
+        &e,
+        Payee { address: s.leaser.clone(), bps: 6_667 },
+        Payee { address: manager.clone(), bps: 3_333 },
+    ];
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { payees: payees.clone(), ..no_terms(&e, &payment) });
+    assert_eq!(payees, s.set_lien.get_lease(&token).unwrap().leasing.payees);
+    s.set_lien.rent(&s.renter, &token, &(3 * DAY));
+
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    assert_eq!(20, s.set_lien.withdraw_earnings(&token));
+    assert_eq!(13, s.payment_client.balance(&s.leaser));
+    assert_eq!(7, s.payment_client.balance(&manager));
+
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(980, s.payment_client.balance(&s.renter));
+    assert_eq!(0, s.payment_client.balance(&s.set_lien.address));
+}
+
+#[test]
+fn test_royalties() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let creator = Address::generate(&e);
+
+    assert_eq!(
+        s.set_lien.try_set_royalty(&token, &creator, &500),
+        Err(Ok(LienError::InvalidRoyalty))
+    );
+    assert_eq!(s.set_lien.try_set_royalty_cap(&10_001), Err(Ok(LienError::InvalidRoyalty)));
+    s.set_lien.set_royalty_cap(&1_000);
+    s.set_lien.set_royalty(&token, &creator, &500);
+    assert_eq!(500, s.set_lien.get_royalty(&token).unwrap().bps);
+
+    s.set_lien.lease(&s.leaser, &token, &s.payment_client.address, &100, &(30 * DAY), &no_terms(&e, &s.payment_client.address));
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+
+    assert_eq!(100, s.set_lien.withdraw_earnings(&token));
+    assert_eq!(5, s.payment_client.balance(&creator));
+    assert_eq!(95, s.payment_client.balance(&s.leaser));
+
+    // Lowering the cap limits royalties already registered
+    s.set_lien.set_royalty_cap(&200);
+    e.ledger().with_mut(|li| li.timestamp = DAY as u64);
+    s.token_client.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    s.set_lien.end_rent(&s.renter, &token);
+    assert_eq!(7, s.payment_client.balance(&creator));
+    assert_eq!(193, s.payment_client.balance(&s.leaser));
+
+    s.set_lien.remove_royalty(&token);
+    assert!(s.set_lien.get_royalty(&token).is_none());
+}
+
+#[test]
+fn test_batch_listing() {
+    let e = Env::default();
+    let s = setup(&e);
+    let payment = s.payment_client.address.clone();
+    let first = s.token_client.address.clone();
+    let second = create_nft(&e, &s, &s.leaser).address;
+    let third = create_nft(&e, &s, &s.leaser).address;
+
+    let entries = vec![
+        &e,
+        LeaseEntry { token: first.clone(), price: 10, duration: 30 * DAY },
+        LeaseEntry { token: second.clone(), price: 20, duration: 10 * DAY },
+        LeaseEntry { token: third.clone(), price: 0, duration: 10 * DAY },
+    ];
+
+    // A bad entry fails the whole batch
+    assert_eq!(
+        s.set_lien.try_lease_many(&s.leaser, &payment, &0, &entries),
+        Err(Ok(LienError::NotLeaseable))
+    );
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Listed));
+
+    let entries = vec![
+        &e,
+        LeaseEntry { token: first.clone(), price: 10, duration: 30 * DAY },
+        LeaseEntry { token: second.clone(), price: 20, duration: 10 * DAY },
+        LeaseEntry { token: third.clone(), price: 30, duration: 10 * DAY },
+    ];
+    s.set_lien.lease_many(&s.leaser, &payment, &0, &entries);
+    assert_eq!(3, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(vec![&e, first.clone(), second.clone(), third.clone()], s.set_lien.get_leased_by_user(&s.leaser));
+    assert_eq!(20, s.set_lien.get_lease(&second).unwrap().leasing.price);
+
+    s.set_lien.update_lease_many(&s.leaser, &vec![
+        &e,
+        LeaseEntry { token: second.clone(), price: 15, duration: 20 * DAY },
+        LeaseEntry { token: third.clone(), price: 25, duration: 20 * DAY },
+    ]);
+    assert_eq!(15, s.set_lien.get_lease(&second).unwrap().leasing.price);
+    assert_eq!(20 * DAY, s.set_lien.get_lease(&third).unwrap().leasing.max_duration);
+
+    s.set_lien.rent(&s.renter, &first, &DAY);
+    assert_eq!(
+        s.set_lien.try_end_lease_many(&s.leaser, &vec![&e, second.clone(), first.clone()]),
+        Err(Ok(LienError::NotListed))
+    );
+    assert_eq!(
+        s.set_lien.try_end_lease_many(&s.renter, &vec![&e, second.clone()]),
+        Err(Ok(LienError::NotLeaser))
+    );
+
+    s.set_lien.end_lease_many(&s.leaser, &vec![&e, second.clone(), third.clone()]);
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Rented));
+    assert_eq!(vec![&e, first.clone()], s.set_lien.get_leased_by_user(&s.leaser));
+    assert!(!s.set_lien.has_lease(&second));
+}
+
+#[test]
+fn test_bundle_listing() {
+    let e = Env::default();
+    let s = setup(&e);
+    let payment = s.payment_client.address.clone();
+    let first = &s.token_client;
+    let second = create_nft(&e, &s, &s.leaser);
+    let third = create_nft(&e, &s, &s.leaser);
+    let nfts = [first, &second, &third];
+    let tokens = vec![&e, first.address.clone(), second.address.clone(), third.address.clone()];
+
+    assert_eq!(
+        s.set_lien.try_lease_bundle(&s.leaser, &vec![&e, first.address.clone()], &payment, &10, &(30 * DAY), &0),
+        Err(Ok(LienError::InvalidBundle))
+    );
+    assert_eq!(
+        s.set_lien.try_lease_bundle(&s.leaser, &vec![&e, first.address.clone(), second.address.clone(), first.address.clone()], &payment, &10, &(30 * DAY), &0),
+        Err(Ok(LienError::InvalidBundle))
+    );
+
+    s.set_lien.lease_bundle(&s.leaser, &tokens, &payment, &10, &(30 * DAY), &0);
+    assert_eq!(1, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(tokens, s.set_lien.get_bundle(&third.address));
+    assert_eq!(tokens, s.set_lien.get_leased_by_user(&s.leaser));
+    // Any member resolves to the bundle's lease
+    assert!(s.set_lien.has_lease(&second.address));
+    assert_eq!(s.leaser, s.set_lien.get_lease(&third.address).unwrap().leasing.leaser);
+
+    // Batch updates resolve members too
+    s.set_lien.update_lease_many(&s.leaser, &vec![&e, LeaseEntry { token: third.address.clone(), price: 20, duration: 30 * DAY }]);
+    assert_eq!(20, s.set_lien.get_lease(&first.address).unwrap().leasing.price);
+
+    // Members cannot be listed on their own
+    assert_eq!(
+        s.set_lien.try_lease(&s.leaser, &second.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment)),
+        Err(Ok(LienError::LeaseAlreadyExists))
+    );
+
+    // Renting the bundle moves every nft
+    s.set_lien.rent(&s.renter, &first.address, &DAY);
+    for nft in nfts {
+        assert_eq!(1, nft.balance(&s.renter));
+        nft.approve(&s.renter, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    }
+    assert_eq!(tokens, s.set_lien.get_rented_by_user(&s.renter));
+
+    s.set_lien.end_rent(&s.renter, &second.address);
+    for nft in nfts {
+        assert_eq!(1, nft.balance(&s.leaser));
+        nft.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    }
+    assert!(!s.set_lien.has_lease(&first.address));
+    assert!(!s.set_lien.has_lease(&second.address));
+    assert_eq!(0, s.set_lien.get_rented_by_user(&s.renter).len());
+    assert_eq!(0, s.set_lien.get_leased_by_user(&s.leaser).len());
+    assert_eq!(0, s.set_lien.get_bundle(&second.address).len());
+
+    // An unreturned bundle is claimed back as a whole
+    s.set_lien.lease_bundle(&s.leaser, &tokens, &payment, &10, &(30 * DAY), &0);
+    s.set_lien.rent(&s.renter, &first.address, &DAY);
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY) as u64);
+    assert_eq!(Some(DAY), s.set_lien.claimable_at(&third.address));
+    s.set_lien.claim_token(&s.leaser, &third.address, &false);
+    for nft in nfts {
+        assert_eq!(1, nft.balance(&s.leaser));
+        assert_eq!(0, nft.balance(&s.renter));
+    }
+    assert_eq!(0, s.set_lien.get_rented_by_user(&s.renter).len());
+    assert_eq!(0, s.set_lien.get_leased_by_user(&s.leaser).len());
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Rented));
+
+    // Two members in one batch end the bundle's lease once
+    for nft in nfts {
+        nft.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    }
+    s.set_lien.lease_bundle(&s.leaser, &tokens, &payment, &10, &(30 * DAY), &0);
+    s.set_lien.end_lease_many(&s.leaser, &vec![&e, second.address.clone(), third.address.clone()]);
+    assert!(!s.set_lien.has_lease(&first.address));
+    assert_eq!(0, s.set_lien.get_listing_count(&LeaseState::Listed));
+    assert_eq!(0, s.set_lien.get_leased_by_user(&s.leaser).len());
+    for nft in nfts {
+        assert_eq!(1, nft.balance(&s.leaser));
+    }
+}
+
+#[test]
+fn test_bundle_royalties() {
+    let e = Env::default();
+    let s = setup(&e);
+    let payment = s.payment_client.address.clone();
+    let second = create_nft(&e, &s, &s.leaser);
+    let creator = Address::generate(&e);
+    let tokens = vec![&e, s.token_client.address.clone(), second.address.clone()];
+
+    // Only the second member's collection takes a royalty
+    s.set_lien.set_royalty_cap(&1_000);
+    s.set_lien.set_royalty(&second.address, &creator, &1_000);
+
+    s.set_lien.lease_bundle(&s.leaser, &tokens, &payment, &100, &(30 * DAY), &0);
+    s.set_lien.rent(&s.renter, &second.address, &DAY);
+
+    // Each member earns half the rent, and pays its royalty on that half
+    assert_eq!(100, s.set_lien.withdraw_earnings(&second.address));
+    assert_eq!(5, s.payment_client.balance(&creator));
+    assert_eq!(95, s.payment_client.balance(&s.leaser));
+}
+
+#[test]
+fn test_admin_handover_and_roles() {
+    let e = Env::default();
+    let s = setup(&e);
+    let new_admin = Address::generate(&e);
+    let pauser = Address::generate(&e);
+
+    assert_eq!(s.set_lien.try_accept_admin(), Err(Ok(LienError::NoPendingAdmin)));
+
+    // The admin keeps control until the new one accepts
+    s.set_lien.propose_admin(&new_admin);
+    assert_eq!(Some(new_admin.clone()), s.set_lien.get_pending_admin());
+    assert_eq!(s.admin, s.set_lien.get_admin());
+
+    s.set_lien.accept_admin();
+    assert_eq!(new_admin, e.auths()[0].0);
+    assert_eq!(new_admin, s.set_lien.get_admin());
+    assert_eq!(None, s.set_lien.get_pending_admin());
+
+    // Roles nobody was granted stay with the admin
+    assert_eq!(new_admin, s.set_lien.get_role(&Role::Pauser));
+    s.set_lien.grant_role(&Role::Pauser, &pauser);
+    assert_eq!(new_admin, e.auths()[0].0);
+    assert_eq!(pauser, s.set_lien.get_role(&Role::Pauser));
+    assert_eq!(new_admin, s.set_lien.get_role(&Role::FeeManager));
+
+    s.set_lien.pause();
+    assert_eq!(pauser, e.auths()[0].0);
+    assert!(s.set_lien.is_paused());
+
+    s.set_lien.queue_action(&AdminAction::SetFee(100));
+    assert_eq!(new_admin, e.auths()[0].0);
+
+    s.set_lien.revoke_role(&Role::Pauser);
+    assert_eq!(new_admin, s.set_lien.get_role(&Role::Pauser));
+    s.set_lien.resume();
+    assert_eq!(new_admin, e.auths()[0].0);
+}
+
+// The contract as first deployed, before storage was versioned
+mod setlien_v1 {
+    soroban_sdk::contractimport!(file = "./build/setlien_v1.wasm");
+}
+
+fn create_setlien_v1<'a>(e: &Env, admin: &Address, payment_token: &Address) -> setlien_v1::Client<'a> {
+    let set_lien = setlien_v1::Client::new(e, &e.register_contract_wasm(None, setlien_v1::WASM));
+    set_lien.initialize(admin, payment_token);
+    set_lien
+}
+
+// Upgrades in place by putting the current code over the old storage
+fn upgrade_setlien<'a>(e: &Env, address: &Address) -> SetLienClient<'a> {
+    SetLienClient::new(e, &e.register_contract(Some(address), SetLien {}))
+}
+
+#[test]
+fn test_migrate_v1_leases() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let leaser = Address::generate(&e);
+    let renter = Address::generate(&e);
+    let payment_client = create_token(&e, &admin);
+    let payment = payment_client.address.clone();
+    payment_client.mint(&renter, &1000);
+
+    // Ending every lease on the baseline contract left an empty list behind
+    let v1 = create_setlien_v1(&e, &admin, &payment);
+    let ended = create_token(&e, &v1.address);
+    ended.mint(&leaser, &1);
+    ended.approve(&leaser, &v1.address, &1, &EXPIRATION_LEDGER);
+    v1.lease(&leaser, &ended.address, &10, &(30 * DAY));
+    v1.end_lease(&leaser, &ended.address);
+
+    let set_lien = upgrade_setlien(&e, &v1.address);
+    assert_eq!(1, set_lien.version());
+    assert_eq!(0, set_lien.migrate(&vec![&e]));
+    assert_eq!(2, set_lien.version());
+    e.as_contract(&set_lien.address, || {
+        assert!(!e.storage().persistent().has(&DataKey::AllListed));
+    });
+
+    // Lease and rent through the baseline contract
+    let v1 = create_setlien_v1(&e, &admin, &payment);
+    let nfts = [create_token(&e, &v1.address), create_token(&e, &v1.address)];
+    for nft in nfts.iter() {
+        nft.mint(&leaser, &1);
+        nft.approve(&leaser, &v1.address, &1, &EXPIRATION_LEDGER);
+        v1.lease(&leaser, &nft.address, &10, &(30 * DAY));
+    }
+    let (listed, rented) = (nfts[0].address.clone(), nfts[1].address.clone());
+    payment_client.approve(&renter, &v1.address, &1000, &EXPIRATION_LEDGER);
+    v1.rent(&renter, &rented, &(2 * DAY));
+
+    let set_lien = upgrade_setlien(&e, &v1.address);
+    assert_eq!(1, set_lien.version());
+
+    assert_eq!(1, set_lien.migrate(&vec![&e, listed.clone()]));
+    // Leases left in the old layout keep the contract at version 1
+    assert_eq!(1, set_lien.version());
+    // Retrying a batch migrates nothing twice
+    assert_eq!(1, set_lien.migrate(&vec![&e, listed.clone(), rented.clone()]));
+    assert_eq!(2, set_lien.version());
+    // The old payment token is the only accepted one
+    assert_eq!(vec![&e, payment.clone()], set_lien.get_payment_tokens());
+
+    let lease = set_lien.get_lease(&rented).unwrap();
+    assert_eq!(LeaseState::Rented, lease.state);
+    assert_eq!(renter, lease.renting.renter);
+    assert_eq!(2 * DAY, lease.renting.rent_duration);
+    assert_eq!(10, lease.renting.price);
+    assert_eq!(payment, lease.leasing.payment_token);
+    assert_eq!(TimeUnit::Day, lease.leasing.unit);
+
+    assert_eq!(vec![&e, listed.clone()], set_lien.get_all_listed());
+    assert_eq!(vec![&e, rented.clone()], set_lien.get_listings(&LeaseState::Rented, &0, &10));
+    e.as_contract(&set_lien.address, || {
+        assert!(!e.storage().persistent().has(&DataKey::AllListed));
+    });
+
+    // Migrated leases work like new ones
+    set_lien.approve_collection(&listed);
+    set_lien.rent(&renter, &listed, &DAY);
+    assert_eq!(1, nfts[0].balance(&renter));
+
+    // and new listings are accepted again
+    let fresh = create_token(&e, &set_lien.address);
+    fresh.mint(&leaser, &1);
+    fresh.approve(&leaser, &set_lien.address, &1, &EXPIRATION_LEDGER);
+    set_lien.approve_collection(&fresh.address);
+    set_lien.lease(&leaser, &fresh.address, &payment, &10, &(30 * DAY), &no_terms(&e, &payment));
+    assert_eq!(LeaseState::Listed, set_lien.get_lease(&fresh.address).unwrap().state);
+}
+
+#[test]
+fn test_timelocked_admin_actions() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let new_admin = Address::generate(&e);
+    assert_eq!(2 * DAY, s.set_lien.get_timelock_delay());
+
+    let id = s.set_lien.queue_action(&AdminAction::SetFee(500));
+    assert_eq!(2 * DAY, s.set_lien.get_queued_action(&id).unwrap().eta);
+    assert_eq!(s.set_lien.try_execute_action(&id), Err(Ok(LienError::ActionNotReady)));
+    assert_eq!(0, s.set_lien.get_fee());
+
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY) as u64);
+    s.set_lien.execute_action(&id);
+    assert_eq!(500, s.set_lien.get_fee());
+    assert_eq!(None, s.set_lien.get_queued_action(&id));
+    assert_eq!(s.set_lien.try_execute_action(&id), Err(Ok(LienError::ActionNotFound)));
+
+    // A cancelled action can no longer run
+    let id = s.set_lien.queue_action(&AdminAction::ChangeNftAdmin(token.clone(), new_admin.clone()));
+    s.set_lien.cancel_action(&id);
+    e.ledger().with_mut(|li| li.timestamp = (5 * DAY) as u64);
+    assert_eq!(s.set_lien.try_execute_action(&id), Err(Ok(LienError::ActionNotFound)));
+    assert_eq!(s.set_lien.address, StellarAssetClient::new(&e, &token).admin());
+
+    // Each action is gated by its own role
+    let manager = Address::generate(&e);
+    s.set_lien.grant_role(&Role::CollectionManager, &manager);
+    let id = s.set_lien.queue_action(&AdminAction::ChangeNftAdmin(token.clone(), new_admin.clone()));
+    assert_eq!(manager, e.auths()[0].0);
+    e.ledger().with_mut(|li| li.timestamp = (7 * DAY) as u64);
+    s.set_lien.execute_action(&id);
+    assert_eq!(manager, e.auths()[0].0);
+    assert_eq!(new_admin, StellarAssetClient::new(&e, &token).admin());
+
+    // A new delay only applies to actions queued after it took effect
+    assert_eq!(
+        s.set_lien.try_queue_action(&AdminAction::SetTimelockDelay(31 * DAY)),
+        Err(Ok(LienError::InvalidDelay))
+    );
+    let id = s.set_lien.queue_action(&AdminAction::SetTimelockDelay(DAY));
+    e.ledger().with_mut(|li| li.timestamp = (9 * DAY) as u64);
+    s.set_lien.execute_action(&id);
+    let id = s.set_lien.queue_action(&AdminAction::SetFee(0));
+    assert_eq!(10 * DAY, s.set_lien.get_queued_action(&id).unwrap().eta);
+}
+
+#[test]
+fn test_settle_overdue_rentals() {
+    let e = Env::default();
+    let s = setup(&e);
+    let token = s.token_client.address.clone();
+    let payment = s.payment_client.address.clone();
+    let keeper = Address::generate(&e);
+
+    assert_eq!(s.set_lien.try_set_settle_bounty(&1_001), Err(Ok(LienError::InvalidFee)));
+    s.set_lien.set_settle_bounty(&500);
+
+    // The bounty comes out of the collateral, the rest is forfeited to the leaser
+    let collateral = Collateral { token: payment.clone(), amount: 100 };
+    s.set_lien.lease(&s.leaser, &token, &payment, &10, &(30 * DAY), &LeaseTerms { collateral, ..no_terms(&e, &payment) });
+    assert_eq!(s.set_lien.try_set_relist(&s.renter, &token, &true), Err(Ok(LienError::NotLeaser)));
+    s.set_lien.set_relist(&s.leaser, &token, &true);
+    s.set_lien.rent(&s.renter, &token, &DAY);
+
+    assert_eq!(s.set_lien.try_settle(&keeper, &token), Err(Ok(LienError::NotClaimable)));
+    e.ledger().with_mut(|li| li.timestamp = (2 * DAY) as u64);
+    // The parties cannot take the bounty for themselves
+    assert_eq!(s.set_lien.try_settle(&s.leaser, &token), Err(Ok(LienError::InvalidKeeper)));
+    assert_eq!(s.set_lien.try_settle(&s.renter, &token), Err(Ok(LienError::InvalidKeeper)));
+    assert_eq!(5, s.set_lien.settle(&keeper, &token));
+    assert_eq!(keeper, e.auths()[0].0);
+
+    assert_eq!(5, s.payment_client.balance(&keeper));
+    assert_eq!(105, s.payment_client.balance(&s.leaser));
+    assert_eq!(890, s.payment_client.balance(&s.renter));
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+    // The leaser asked for the token to be relisted
+    assert_eq!(LeaseState::Listed, s.set_lien.get_lease(&token).unwrap().state);
+    assert_eq!(0, s.set_lien.get_rented_by_user(&s.renter).len());
+    assert_eq!(s.set_lien.try_settle(&keeper, &token), Err(Ok(LienError::NotRented)));
+
+    // Without collateral the bounty is paid from the fees the protocol kept
+    s.set_lien.end_lease(&s.leaser, &token);
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    run_admin_action(&e, &s, AdminAction::SetFee(1_000));
+    s.set_lien.lease(&s.leaser, &token, &payment, &100, &(30 * DAY), &no_terms(&e, &payment));
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+    assert_eq!(20, s.set_lien.get_accrued_fees(&payment));
+
+    e.ledger().with_mut(|li| li.timestamp = (5 * DAY) as u64);
+    assert_eq!(9, s.set_lien.settle(&keeper, &token));
+    assert_eq!(14, s.payment_client.balance(&keeper));
+    assert_eq!(11, s.set_lien.get_accrued_fees(&payment));
+    assert_eq!(1, s.token_client.balance(&s.leaser));
+    assert!(!s.set_lien.has_lease(&token));
+    assert_eq!(false, s.set_lien.get_relist(&token));
+
+    // The bounty never exceeds the fee the rental itself paid
+    s.token_client.approve(&s.leaser, &s.set_lien.address, &1, &EXPIRATION_LEDGER);
+    run_admin_action(&e, &s, AdminAction::SetFee(100));
+    s.set_lien.set_settle_bounty(&1_000);
+    s.set_lien.lease(&s.leaser, &token, &payment, &100, &(30 * DAY), &no_terms(&e, &payment));
+    s.set_lien.rent(&s.renter, &token, &(2 * DAY));
+    assert_eq!(2, s.set_lien.get_escrow(&token).unwrap().fee);
+    assert_eq!(13, s.set_lien.get_accrued_fees(&payment));
+
+    e.ledger().with_mut(|li| li.timestamp = (8 * DAY) as u64);
+    assert_eq!(2, s.set_lien.settle(&keeper, &token));
+    assert_eq!(16, s.payment_client.balance(&keeper));
+    assert_eq!(11, s.set_lien.get_accrued_fees(&payment));
+}
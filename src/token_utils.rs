@@ -1,9 +1,7 @@
 
-use core::ops::Add;
-
 use soroban_sdk::{Address, Env, log};
 
-use crate::{token, storage_types::INSTANCE_BUMP_AMOUNT};
+use crate::token;
 
 pub fn balance(e: &Env, token: &Address, id: &Address) -> i128 {
     token::Client::new(e, token).balance(id)
@@ -17,8 +15,27 @@ pub fn transfer_from(e: &Env, token: &Address, from: &Address, to: &Address, amo
     token_client.transfer_from(&contract_address, from, to, &amount);
 }
 
+pub fn transfer(e: &Env, token: &Address, to: &Address, amount: i128) {
+    let token_client = token::Client::new(e, token);
+    token_client.transfer(&e.current_contract_address(), to, &amount);
+}
+
 pub fn make_admin(e: &Env, token: &Address, to: &Address) {
-    soroban_sdk::token::StellarAssetClient::new(e, token).set_admin(&to);
+    soroban_sdk::token::StellarAssetClient::new(e, token).set_admin(to);
+}
+
+pub fn read_admin(e: &Env, token: &Address) -> Option<Address> {
+    match soroban_sdk::token::StellarAssetClient::new(e, token).try_admin() {
+        Ok(Ok(admin)) => Some(admin),
+        _ => None,
+    }
+}
+
+pub fn read_decimals(e: &Env, token: &Address) -> Option<u32> {
+    match soroban_sdk::token::TokenClient::new(e, token).try_decimals() {
+        Ok(Ok(decimals)) => Some(decimals),
+        _ => None,
+    }
 }
 
 pub fn set_authorized(e: &Env, token: &Address, to: &Address) {
@@ -29,10 +46,6 @@ pub fn set_unauthorized(e: &Env, token: &Address, to: &Address) {
     soroban_sdk::token::StellarAssetClient::new(e, token).set_authorized(to, &false);
 }
 
-pub fn is_authorized(e: &Env, token: &Address, to: &Address) -> bool {
-    soroban_sdk::token::StellarAssetClient::new(e, token).authorized(to)
-}
-
 pub fn get_allowance(e: &Env, token: &Address, from: &Address, spender: &Address) -> i128 {
     soroban_sdk::token::TokenClient::new(e, token).allowance(from, spender)
 }